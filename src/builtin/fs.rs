@@ -0,0 +1,165 @@
+use std::time::UNIX_EPOCH;
+
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::{
+	builtin,
+	runtime::{ExecutionError, functions::FunctionLibrary, output::Output, scope::ScopeStack},
+};
+
+fn io_output<T>(res: std::io::Result<T>, f: impl FnOnce(T) -> Output) -> Output {
+	match res {
+		Ok(value) => f(value),
+		Err(err) => Output::new_falsy_with(err.to_string().into()),
+	}
+}
+
+async fn read(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let path = match args.as_slice() {
+		[path] => path,
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(io_output(fs::read_to_string(path.value()).await, |content| {
+		Output::new_truthy_with(content.into())
+	}))
+}
+
+async fn write(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (path, content) = match args.as_slice() {
+		[path, content] => (path, content),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(io_output(fs::write(path.value(), content.value()).await, |_| {
+		Output::new_truthy()
+	}))
+}
+
+async fn append_file(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (path, content) = match args.as_slice() {
+		[path, content] => (path, content),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let file = fs::OpenOptions::new()
+		.append(true)
+		.create(true)
+		.open(path.value())
+		.await;
+	let res = match file {
+		Ok(mut file) => file.write_all(content.value().as_bytes()).await,
+		Err(err) => Err(err),
+	};
+	Ok(io_output(res, |_| Output::new_truthy()))
+}
+
+async fn exists(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let path = match args.as_slice() {
+		[path] => path,
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(match fs::try_exists(path.value()).await {
+		Ok(true) => Output::new_truthy(),
+		Ok(false) => Output::new_falsy(),
+		Err(err) => Output::new_falsy_with(err.to_string().into()),
+	})
+}
+
+async fn ls(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let dir = match args.as_slice() {
+		[] => ".",
+		[dir] => dir.value(),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let mut entries = match fs::read_dir(dir).await {
+		Ok(entries) => entries,
+		Err(err) => return Ok(Output::new_falsy_with(err.to_string().into())),
+	};
+	let mut names = vec![];
+	loop {
+		match entries.next_entry().await {
+			Ok(Some(entry)) => names.push(entry.file_name().to_string_lossy().into_owned()),
+			Ok(None) => break,
+			Err(err) => return Ok(Output::new_falsy_with(err.to_string().into())),
+		}
+	}
+	names.sort();
+	Ok(Output::new_truthy_with(names.join("\n").into()))
+}
+
+async fn mkdir(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let path = match args.as_slice() {
+		[path] => path,
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(io_output(fs::create_dir_all(path.value()).await, |_| {
+		Output::new_truthy()
+	}))
+}
+
+async fn rm(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let path = match args.as_slice() {
+		[path] => path,
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let res = match fs::symlink_metadata(path.value()).await {
+		Ok(meta) if meta.is_dir() => fs::remove_dir(path.value()).await,
+		Ok(_) => fs::remove_file(path.value()).await,
+		Err(err) => Err(err),
+	};
+	Ok(io_output(res, |_| Output::new_truthy()))
+}
+
+async fn rm_all(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let path = match args.as_slice() {
+		[path] => path,
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let res = match fs::symlink_metadata(path.value()).await {
+		Ok(meta) if meta.is_dir() => fs::remove_dir_all(path.value()).await,
+		Ok(_) => fs::remove_file(path.value()).await,
+		Err(err) => Err(err),
+	};
+	Ok(io_output(res, |_| Output::new_truthy()))
+}
+
+async fn stat(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let path = match args.as_slice() {
+		[path] => path,
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(io_output(fs::metadata(path.value()).await, |meta| {
+		let kind = if meta.is_dir() {
+			"dir"
+		} else if meta.is_file() {
+			"file"
+		} else {
+			"other"
+		};
+		let modified = meta
+			.modified()
+			.ok()
+			.and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+			.map(|time| time.as_secs());
+		let stat = serde_json::json!({
+			"type": kind,
+			"size": meta.len(),
+			"readonly": meta.permissions().readonly(),
+			"modified": modified,
+		});
+		Output::new_truthy_with(stat.to_string().into())
+	}))
+}
+
+pub fn build() -> FunctionLibrary {
+	let mut library = FunctionLibrary::new();
+	builtin!(library, read, "path");
+	builtin!(library, write, "path", "content");
+	builtin!(library, append_file, "path", "content");
+	builtin!(library, exists, "path");
+	builtin!(library, ls,);
+	builtin!(library, ls, "dir");
+	builtin!(library, mkdir, "path");
+	builtin!(library, rm, "path");
+	builtin!(library, rm_all, "path");
+	builtin!(library, stat, "path");
+	library
+}
@@ -1,8 +1,33 @@
 use crate::{
 	builtin,
-	runtime::{ExecutionError, functions::FunctionLibrary, output::Output, scope::ScopeStack},
+	runtime::{ExecutionError, call_function, functions::FunctionLibrary, handles::Held, output::{Output, decode_list, encode_list}, scope::ScopeStack},
 };
 
+fn join_separator(separator: Option<&Output>) -> &str {
+	match separator {
+		Some(s) => s.value(),
+		None => " ",
+	}
+}
+
+// Sorts numerically when every key is a number, and lexically otherwise. The
+// sort is stable, so items with equal keys keep their order.
+fn sort_keyed(items: Vec<(String, &str)>) -> Vec<&str> {
+	let numbers: Option<Vec<f64>> = items.iter().map(|(key, _)| key.parse().ok()).collect();
+	match numbers {
+		Some(numbers) => {
+			let mut items: Vec<(f64, &str)> = numbers.into_iter().zip(items.into_iter().map(|(_, item)| item)).collect();
+			items.sort_by(|a, b| a.0.total_cmp(&b.0));
+			items.into_iter().map(|(_, item)| item).collect()
+		}
+		None => {
+			let mut items = items;
+			items.sort_by(|a, b| a.0.cmp(&b.0));
+			items.into_iter().map(|(_, item)| item).collect()
+		}
+	}
+}
+
 async fn range(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
 	#[rustfmt::skip]
 	let (min, max, step, separator): (Result<i64, _>, Result<i64, _>, Result<i64, _>, _) = match args.as_slice() {
@@ -100,6 +125,112 @@ async fn len(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) ->
 	))
 }
 
+async fn map<'env, 'stack>(fl: &FunctionLibrary, stack: &'stack mut ScopeStack<'env>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (fname, arr, separator) = match args.as_slice() {
+		[fname, arr] => (fname, arr, None),
+		[fname, arr, separator] => (fname, arr, Some(separator)),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let mut mapped = vec![];
+	let mut held = Held::default();
+	for item in arr.split_iter(separator) {
+		let output = call_function(fl, stack, fname.value(), vec![arr.part(item)]).await?;
+		mapped.push(output.value().to_owned());
+		held.merge(output.held());
+	}
+	let mut output = Output::new_truthy_with(mapped.join(join_separator(separator)).into());
+	output.hold(&held);
+	Ok(output)
+}
+
+async fn filter<'env, 'stack>(fl: &FunctionLibrary, stack: &'stack mut ScopeStack<'env>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (fname, arr, separator) = match args.as_slice() {
+		[fname, arr] => (fname, arr, None),
+		[fname, arr, separator] => (fname, arr, Some(separator)),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let mut kept = vec![];
+	for item in arr.split_iter(separator) {
+		let output = call_function(fl, stack, fname.value(), vec![arr.part(item)]).await?;
+		if output.is_truthy() {
+			kept.push(item);
+		}
+	}
+	Ok(Output::new_truthy_with(kept.join(join_separator(separator)).into()))
+}
+
+async fn reduce<'env, 'stack>(fl: &FunctionLibrary, stack: &'stack mut ScopeStack<'env>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (fname, init, arr, separator) = match args.as_slice() {
+		[fname, init, arr] => (fname, init, arr, None),
+		[fname, init, arr, separator] => (fname, init, arr, Some(separator)),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let mut acc = init.clone();
+	for item in arr.split_iter(separator) {
+		acc = call_function(fl, stack, fname.value(), vec![acc, arr.part(item)]).await?;
+	}
+	Ok(acc)
+}
+
+async fn sort(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (arr, separator) = match args.as_slice() {
+		[arr] => (arr, None),
+		[arr, separator] => (arr, Some(separator)),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let items = arr.split_iter(separator).map(|item| (item.to_owned(), item)).collect();
+	Ok(Output::new_truthy_with(sort_keyed(items).join(join_separator(separator)).into()))
+}
+
+async fn sort_by<'env, 'stack>(fl: &FunctionLibrary, stack: &'stack mut ScopeStack<'env>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (fname, arr, separator) = match args.as_slice() {
+		[fname, arr] => (fname, arr, None),
+		[fname, arr, separator] => (fname, arr, Some(separator)),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let mut items = vec![];
+	for item in arr.split_iter(separator) {
+		let key = call_function(fl, stack, fname.value(), vec![arr.part(item)]).await?;
+		items.push((key.value().to_owned(), item));
+	}
+	Ok(Output::new_truthy_with(sort_keyed(items).join(join_separator(separator)).into()))
+}
+
+async fn list(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	Ok(Output::new_truthy_with(
+		encode_list(args.iter().map(|arg| arg.value())).into(),
+	))
+}
+
+async fn nth(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (list, i) = match args.as_slice() {
+		[list, i] => (list, i.try_into()),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let i: i64 = match i {
+		Ok(x) if x >= 0 => x,
+		_ => return Ok(Output::new_falsy()),
+	};
+	let Some(items) = decode_list(list.value()) else {
+		return Ok(Output::new_falsy());
+	};
+	Ok(match items.get(i as usize) {
+		Some(item) => Output::new_truthy_with(item.to_string().into()),
+		None => Output::new_falsy(),
+	})
+}
+
+async fn list_len(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let list = match args.as_slice() {
+		[list] => list,
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(match decode_list(list.value()) {
+		Some(items) => Output::new_truthy_with(items.len().to_string().into()),
+		None => Output::new_falsy(),
+	})
+}
+
 pub fn build() -> FunctionLibrary {
 	let mut library = FunctionLibrary::new();
 	builtin!(library, range, "max");
@@ -114,5 +245,18 @@ pub fn build() -> FunctionLibrary {
 	builtin!(library, last, "arr", "n", "sep");
 	builtin!(library, append, "left", "right");
 	builtin!(library, append, "left", "right", "sep");
+	builtin!(library, list, "%items");
+	builtin!(library, nth, "list", "i");
+	builtin!(library, list_len, "list");
+	builtin!(library, map, "fname", "arr");
+	builtin!(library, map, "fname", "arr", "sep");
+	builtin!(library, filter, "fname", "arr");
+	builtin!(library, filter, "fname", "arr", "sep");
+	builtin!(library, reduce, "fname", "init", "arr");
+	builtin!(library, reduce, "fname", "init", "arr", "sep");
+	builtin!(library, sort, "arr");
+	builtin!(library, sort, "arr", "sep");
+	builtin!(library, sort_by, "fname", "arr");
+	builtin!(library, sort_by, "fname", "arr", "sep");
 	library
 }
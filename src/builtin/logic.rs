@@ -1,6 +1,8 @@
+use std::cmp::Ordering;
+
 use crate::{
 	builtin, builtin_alias,
-	runtime::{ExecutionError, functions::FunctionLibrary, output::Output, scope::ScopeStack},
+	runtime::{ExecutionError, functions::FunctionLibrary, output::{Output, join_outputs}, scope::ScopeStack},
 };
 
 async fn truthy(_: &FunctionLibrary, _: &mut ScopeStack<'_>, _: Vec<Output>) -> Result<Output, ExecutionError> {
@@ -11,88 +13,47 @@ async fn falsy(_: &FunctionLibrary, _: &mut ScopeStack<'_>, _: Vec<Output>) -> R
 	Ok(Output::new_falsy())
 }
 
+// Compares two numbers, exactly if both are integers, so that large IDs
+// aren't rounded into each other.
+fn compare(args: &[Output], test: fn(Ordering) -> bool) -> Result<Output, ExecutionError> {
+	let ordering = match args {
+		[a, b] => match (i64::try_from(a), i64::try_from(b)) {
+			(Ok(a), Ok(b)) => Some(a.cmp(&b)),
+			_ => match (f64::try_from(a), f64::try_from(b)) {
+				(Ok(a), Ok(b)) => a.partial_cmp(&b),
+				_ => None,
+			},
+		},
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(match ordering {
+		Some(ordering) if test(ordering) => Output::new_truthy(),
+		_ => Output::new_falsy(),
+	})
+}
+
 async fn eq(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
-	match args.as_slice() {
-		[a, b] => Ok({
-			let a: Result<f64, _> = a.try_into();
-			let b: Result<f64, _> = b.try_into();
-			match (a, b) {
-				(Ok(a), Ok(b)) => Output::new("".into(), if a == b { 0 } else { 1 }),
-				_ => Output::new_falsy(),
-			}
-		}),
-		_ => Err(ExecutionError::InternalError),
-	}
+	compare(&args, Ordering::is_eq)
 }
 
 async fn neq(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
-	match args.as_slice() {
-		[a, b] => Ok({
-			let a: Result<f64, _> = a.try_into();
-			let b: Result<f64, _> = b.try_into();
-			match (a, b) {
-				(Ok(a), Ok(b)) => Output::new("".into(), if a != b { 0 } else { 1 }),
-				_ => Output::new_falsy(),
-			}
-		}),
-		_ => Err(ExecutionError::InternalError),
-	}
+	compare(&args, Ordering::is_ne)
 }
 
 async fn gt(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
-	match args.as_slice() {
-		[a, b] => Ok({
-			let a: Result<f64, _> = a.try_into();
-			let b: Result<f64, _> = b.try_into();
-			match (a, b) {
-				(Ok(a), Ok(b)) => Output::new("".into(), if a > b { 0 } else { 1 }),
-				_ => Output::new_falsy(),
-			}
-		}),
-		_ => Err(ExecutionError::InternalError),
-	}
+	compare(&args, Ordering::is_gt)
 }
 
 async fn lt(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
-	match args.as_slice() {
-		[a, b] => Ok({
-			let a: Result<f64, _> = a.try_into();
-			let b: Result<f64, _> = b.try_into();
-			match (a, b) {
-				(Ok(a), Ok(b)) => Output::new("".into(), if a < b { 0 } else { 1 }),
-				_ => Output::new_falsy(),
-			}
-		}),
-		_ => Err(ExecutionError::InternalError),
-	}
+	compare(&args, Ordering::is_lt)
 }
 
 async fn geq(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
-	match args.as_slice() {
-		[a, b] => Ok({
-			let a: Result<f64, _> = a.try_into();
-			let b: Result<f64, _> = b.try_into();
-			match (a, b) {
-				(Ok(a), Ok(b)) => Output::new("".into(), if a >= b { 0 } else { 1 }),
-				_ => Output::new_falsy(),
-			}
-		}),
-		_ => Err(ExecutionError::InternalError),
-	}
+	compare(&args, Ordering::is_ge)
 }
 
 async fn leq(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
-	match args.as_slice() {
-		[a, b] => Ok({
-			let a: Result<f64, _> = a.try_into();
-			let b: Result<f64, _> = b.try_into();
-			match (a, b) {
-				(Ok(a), Ok(b)) => Output::new("".into(), if a <= b { 0 } else { 1 }),
-				_ => Output::new_falsy(),
-			}
-		}),
-		_ => Err(ExecutionError::InternalError),
-	}
+	compare(&args, Ordering::is_le)
 }
 
 async fn like(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
@@ -129,6 +90,18 @@ async fn or(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> R
 	}
 }
 
+async fn all(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let output = join_outputs(args.iter());
+	let code = if args.iter().all(|arg| arg.is_truthy()) { 0 } else { 1 };
+	Ok(Output::new(output.value().to_owned().into(), code))
+}
+
+async fn any(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let output = join_outputs(args.iter());
+	let code = if args.iter().any(|arg| arg.is_truthy()) { 0 } else { 1 };
+	Ok(Output::new(output.value().to_owned().into(), code))
+}
+
 async fn not(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
 	match args.as_slice() {
 		[a] => Ok(Output::new("".into(), if a.is_truthy() { 1 } else { 0 })),
@@ -150,5 +123,7 @@ pub fn build() -> FunctionLibrary {
 	builtin!(library, and, "a", "b");
 	builtin!(library, or, "a", "b");
 	builtin!(library, not, "a");
+	builtin!(library, all, "%args");
+	builtin!(library, any, "%args");
 	library
 }
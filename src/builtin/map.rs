@@ -0,0 +1,89 @@
+use crate::{
+	builtin,
+	runtime::{ExecutionError, functions::FunctionLibrary, output::{Output, encode_list}, scope::ScopeStack},
+};
+
+async fn map_new(fl: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	match args.as_slice() {
+		[] => Ok(fl.maps().create()),
+		_ => Err(ExecutionError::InternalError),
+	}
+}
+
+async fn map_set(fl: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (map, key, value) = match args.as_slice() {
+		[map, key, value] => (map, key, value),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(match fl.maps().insert(map.value(), key, value.clone()) {
+		Some(_) => Output::new_truthy(),
+		None => Output::new_falsy(),
+	})
+}
+
+async fn map_get(fl: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (map, key) = match args.as_slice() {
+		[map, key] => (map, key),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(match fl.maps().with(map.value(), |map| map.get(key.value()).cloned()).flatten() {
+		Some(value) => value.part(value.value()),
+		None => Output::new_falsy(),
+	})
+}
+
+async fn map_has(fl: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (map, key) = match args.as_slice() {
+		[map, key] => (map, key),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(match fl.maps().with(map.value(), |map| map.contains_key(key.value())) {
+		Some(true) => Output::new_truthy(),
+		_ => Output::new_falsy(),
+	})
+}
+
+async fn map_del(fl: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (map, key) = match args.as_slice() {
+		[map, key] => (map, key),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(match fl.maps().with(map.value(), |map| map.remove(key.value())).flatten() {
+		Some(value) => value.part(value.value()),
+		None => Output::new_falsy(),
+	})
+}
+
+async fn map_keys(fl: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let map = match args.as_slice() {
+		[map] => map,
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(match fl.maps().with(map.value(), |map| encode_list(map.keys().map(|key| key.as_str()))) {
+		Some(keys) => Output::new_truthy_with(keys.into()),
+		None => Output::new_falsy(),
+	})
+}
+
+async fn map_len(fl: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let map = match args.as_slice() {
+		[map] => map,
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(match fl.maps().with(map.value(), |map| map.len()) {
+		Some(len) => Output::new_truthy_with(len.to_string().into()),
+		None => Output::new_falsy(),
+	})
+}
+
+pub fn build() -> FunctionLibrary {
+	let mut library = FunctionLibrary::new();
+	builtin!(library, map_new,);
+	builtin!(library, map_set, "map", "key", "value");
+	builtin!(library, map_get, "map", "key");
+	builtin!(library, map_has, "map", "key");
+	builtin!(library, map_del, "map", "key");
+	builtin!(library, map_keys, "map");
+	builtin!(library, map_len, "map");
+	library
+}
@@ -1,12 +1,15 @@
 use rand::Rng;
 
 use crate::{
-	builtin,
+	builtin, builtin_alias,
 	runtime::{ExecutionError, functions::FunctionLibrary, output::Output, scope::ScopeStack},
 };
 
-fn numbers(args: Vec<Output>) -> Option<(f64, Vec<f64>)> {
-	match args.as_slice() {
+fn numbers<T>(args: &[Output]) -> Option<(T, Vec<T>)>
+where
+	T: for<'a> TryFrom<&'a Output>,
+{
+	match args {
 		[first, rest @ ..] => {
 			let first = match first.try_into() {
 				Ok(x) => x,
@@ -26,8 +29,28 @@ fn numbers(args: Vec<Output>) -> Option<(f64, Vec<f64>)> {
 	}
 }
 
+// Integer results are computed exactly, so they can't overflow silently.
+fn integer(result: Option<i64>) -> Output {
+	match result {
+		Some(val) => Output::new_truthy_with(val.to_string().into()),
+		None => Output::new_falsy(),
+	}
+}
+
+// Fails on results such as sqrt(-1) or ln(0) that aren't finite numbers.
+fn float(result: f64) -> Output {
+	if result.is_finite() {
+		Output::new_truthy_with(result.to_string().into())
+	} else {
+		Output::new_falsy()
+	}
+}
+
 async fn add(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
-	Ok(match numbers(args) {
+	if let Some((first, numbers)) = numbers::<i64>(&args) {
+		return Ok(integer(numbers.into_iter().try_fold(first, i64::checked_add)));
+	}
+	Ok(match numbers::<f64>(&args) {
 		Some((first, numbers)) => {
 			let mut val = first;
 			for x in numbers {
@@ -40,7 +63,10 @@ async fn add(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) ->
 }
 
 async fn sub(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
-	Ok(match numbers(args) {
+	if let Some((first, numbers)) = numbers::<i64>(&args) {
+		return Ok(integer(numbers.into_iter().try_fold(first, i64::checked_sub)));
+	}
+	Ok(match numbers::<f64>(&args) {
 		Some((first, numbers)) => {
 			let mut val = first;
 			for x in numbers {
@@ -53,7 +79,10 @@ async fn sub(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) ->
 }
 
 async fn mul(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
-	Ok(match numbers(args) {
+	if let Some((first, numbers)) = numbers::<i64>(&args) {
+		return Ok(integer(numbers.into_iter().try_fold(first, i64::checked_mul)));
+	}
+	Ok(match numbers::<f64>(&args) {
 		Some((first, numbers)) => {
 			let mut val = first;
 			for x in numbers {
@@ -66,7 +95,7 @@ async fn mul(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) ->
 }
 
 async fn div(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
-	Ok(match numbers(args) {
+	Ok(match numbers::<f64>(&args) {
 		Some((first, numbers)) => {
 			let mut val = first;
 			for x in numbers {
@@ -79,7 +108,10 @@ async fn div(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) ->
 }
 
 async fn max(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
-	Ok(match numbers(args) {
+	if let Some((first, numbers)) = numbers::<i64>(&args) {
+		return Ok(integer(Some(numbers.into_iter().fold(first, i64::max))));
+	}
+	Ok(match numbers::<f64>(&args) {
 		Some((first, numbers)) => {
 			let mut val = first;
 			for x in numbers {
@@ -94,7 +126,10 @@ async fn max(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) ->
 }
 
 async fn min(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
-	Ok(match numbers(args) {
+	if let Some((first, numbers)) = numbers::<i64>(&args) {
+		return Ok(integer(Some(numbers.into_iter().fold(first, i64::min))));
+	}
+	Ok(match numbers::<f64>(&args) {
 		Some((first, numbers)) => {
 			let mut val = first;
 			for x in numbers {
@@ -108,6 +143,81 @@ async fn min(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) ->
 	})
 }
 
+async fn idiv(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	Ok(match numbers::<i64>(&args) {
+		Some((first, numbers)) => integer(numbers.into_iter().try_fold(first, i64::checked_div)),
+		None => Output::new_falsy(),
+	})
+}
+
+async fn modulo(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	Ok(match numbers::<i64>(&args) {
+		Some((first, numbers)) => integer(numbers.into_iter().try_fold(first, i64::checked_rem_euclid)),
+		None => Output::new_falsy(),
+	})
+}
+
+async fn pow(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (base, exp) = match args.as_slice() {
+		[base, exp] => (base, exp),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	if let (Ok(base), Ok(exp)) = (i64::try_from(base), i64::try_from(exp)) {
+		if let Ok(exp) = u32::try_from(exp) {
+			return Ok(integer(base.checked_pow(exp)));
+		}
+	}
+	Ok(match (f64::try_from(base), f64::try_from(exp)) {
+		(Ok(base), Ok(exp)) => float(base.powf(exp)),
+		_ => Output::new_falsy(),
+	})
+}
+
+async fn band(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	Ok(match numbers::<i64>(&args) {
+		Some((first, numbers)) => integer(Some(numbers.into_iter().fold(first, |a, b| a & b))),
+		None => Output::new_falsy(),
+	})
+}
+
+async fn bor(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	Ok(match numbers::<i64>(&args) {
+		Some((first, numbers)) => integer(Some(numbers.into_iter().fold(first, |a, b| a | b))),
+		None => Output::new_falsy(),
+	})
+}
+
+async fn bxor(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	Ok(match numbers::<i64>(&args) {
+		Some((first, numbers)) => integer(Some(numbers.into_iter().fold(first, |a, b| a ^ b))),
+		None => Output::new_falsy(),
+	})
+}
+
+// Shifts by `n` bits, giving up if that would push set bits out of `x`.
+fn shift(args: &[Output], left: bool) -> Result<Output, ExecutionError> {
+	let (x, n) = match args {
+		[x, n] => (i64::try_from(x), i64::try_from(n)),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let (Ok(x), Some(n)) = (x, n.ok().and_then(|n| u32::try_from(n).ok())) else {
+		return Ok(Output::new_falsy());
+	};
+	Ok(integer(if left {
+		x.checked_shl(n).filter(|shifted| shifted >> n == x)
+	} else {
+		x.checked_shr(n)
+	}))
+}
+
+async fn shl(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	shift(&args, true)
+}
+
+async fn shr(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	shift(&args, false)
+}
+
 async fn floor(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
 	let number: Result<f64, _> = match args.as_slice() {
 		[number] => number.try_into(),
@@ -130,6 +240,135 @@ async fn ceil(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) ->
 	})
 }
 
+fn unary(args: &[Output], f: fn(f64) -> f64) -> Result<Output, ExecutionError> {
+	let x: Result<f64, _> = match args {
+		[x] => x.try_into(),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(match x {
+		Ok(x) => float(f(x)),
+		Err(_) => Output::new_falsy(),
+	})
+}
+
+async fn sqrt(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	unary(&args, f64::sqrt)
+}
+
+async fn exp(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	unary(&args, f64::exp)
+}
+
+async fn ln(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	unary(&args, f64::ln)
+}
+
+async fn sin(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	unary(&args, f64::sin)
+}
+
+async fn cos(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	unary(&args, f64::cos)
+}
+
+async fn tan(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	unary(&args, f64::tan)
+}
+
+async fn asin(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	unary(&args, f64::asin)
+}
+
+async fn acos(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	unary(&args, f64::acos)
+}
+
+async fn atan(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	unary(&args, f64::atan)
+}
+
+async fn log(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	match args.as_slice() {
+		[_] => unary(&args, f64::log10),
+		[x, base] => Ok(match (f64::try_from(x), f64::try_from(base)) {
+			(Ok(x), Ok(base)) => float(x.log(base)),
+			_ => Output::new_falsy(),
+		}),
+		_ => Err(ExecutionError::InternalError),
+	}
+}
+
+async fn atan2(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (y, x) = match args.as_slice() {
+		[y, x] => (f64::try_from(y), f64::try_from(x)),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(match (y, x) {
+		(Ok(y), Ok(x)) => Output::new_truthy_with(y.atan2(x).to_string().into()),
+		_ => Output::new_falsy(),
+	})
+}
+
+async fn abs(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	match args.as_slice() {
+		[x] => match i64::try_from(x) {
+			Ok(x) => Ok(integer(x.checked_abs())),
+			Err(_) => unary(&args, f64::abs),
+		},
+		_ => Err(ExecutionError::InternalError),
+	}
+}
+
+async fn sign(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let x: Result<f64, _> = match args.as_slice() {
+		[x] => x.try_into(),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(match x {
+		Ok(x) if x > 0.0 => Output::new_truthy_with("1".into()),
+		Ok(x) if x < 0.0 => Output::new_truthy_with("-1".into()),
+		Ok(_) => Output::new_truthy_with("0".into()),
+		Err(_) => Output::new_falsy(),
+	})
+}
+
+async fn round(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (x, digits) = match args.as_slice() {
+		[x] => (f64::try_from(x), Ok(0)),
+		[x, digits] => (f64::try_from(x), i64::try_from(digits)),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let (Ok(x), Some(digits)) = (x, digits.ok().and_then(|digits| i32::try_from(digits).ok()).filter(|digits| *digits >= 0)) else {
+		return Ok(Output::new_falsy());
+	};
+	// Past the precision of a float there's nothing left to round.
+	let scale = 10f64.powi(digits);
+	let rounded = if (x * scale).is_finite() { (x * scale).round() / scale } else { x };
+	Ok(Output::new_truthy_with(rounded.to_string().into()))
+}
+
+async fn clamp(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (x, low, high) = match args.as_slice() {
+		[x, low, high] => (x, low, high),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	if let (Ok(x), Ok(low), Ok(high)) = (i64::try_from(x), i64::try_from(low), i64::try_from(high)) {
+		return Ok(integer((low <= high).then(|| x.clamp(low, high))));
+	}
+	Ok(match (f64::try_from(x), f64::try_from(low), f64::try_from(high)) {
+		(Ok(x), Ok(low), Ok(high)) if low <= high => Output::new_truthy_with(x.clamp(low, high).to_string().into()),
+		_ => Output::new_falsy(),
+	})
+}
+
+async fn pi(_: &FunctionLibrary, _: &mut ScopeStack<'_>, _: Vec<Output>) -> Result<Output, ExecutionError> {
+	Ok(Output::new_truthy_with(std::f64::consts::PI.to_string().into()))
+}
+
+async fn e(_: &FunctionLibrary, _: &mut ScopeStack<'_>, _: Vec<Output>) -> Result<Output, ExecutionError> {
+	Ok(Output::new_truthy_with(std::f64::consts::E.to_string().into()))
+}
+
 async fn random(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
 	let (min, max) = match args.as_slice() {
 		[max] => (Ok(0), max.try_into()),
@@ -158,8 +397,35 @@ pub fn build() -> FunctionLibrary {
 	builtin!(library, div, "first", "%others");
 	builtin!(library, max, "first", "%others");
 	builtin!(library, min, "first", "%others");
+	builtin!(library, idiv, "first", "%others");
+	builtin_alias!(library, modulo, "mod", "first", "%others");
+	builtin!(library, pow, "base", "exp");
+	builtin!(library, band, "first", "%others");
+	builtin!(library, bor, "first", "%others");
+	builtin!(library, bxor, "first", "%others");
+	builtin!(library, shl, "x", "n");
+	builtin!(library, shr, "x", "n");
 	builtin!(library, floor, "x");
 	builtin!(library, ceil, "x");
+	builtin!(library, sqrt, "x");
+	builtin!(library, exp, "x");
+	builtin!(library, ln, "x");
+	builtin!(library, log, "x");
+	builtin!(library, log, "x", "base");
+	builtin!(library, sin, "x");
+	builtin!(library, cos, "x");
+	builtin!(library, tan, "x");
+	builtin!(library, asin, "x");
+	builtin!(library, acos, "x");
+	builtin!(library, atan, "x");
+	builtin!(library, atan2, "y", "x");
+	builtin!(library, abs, "x");
+	builtin!(library, sign, "x");
+	builtin!(library, round, "x");
+	builtin!(library, round, "x", "digits");
+	builtin!(library, clamp, "x", "low", "high");
+	builtin!(library, pi,);
+	builtin!(library, e,);
 	builtin!(library, random, "max");
 	builtin!(library, random, "min", "max");
 	library
@@ -1,8 +1,11 @@
+pub mod fs;
 pub mod iter;
 pub mod logic;
 mod macros;
+pub mod map;
 pub mod math;
 pub mod net;
+pub mod process;
 pub mod std;
 pub mod str;
 pub mod json;
\ No newline at end of file
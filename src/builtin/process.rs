@@ -0,0 +1,119 @@
+use std::{
+	collections::HashMap,
+	process::Stdio,
+	sync::{Arc, Mutex},
+};
+
+use serde_json::{Map, Value};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use crate::{
+	builtin_state,
+	runtime::{ExecutionError, functions::FunctionLibrary, output::Output, scope::ScopeStack},
+};
+
+fn json_str(value: &Value) -> String {
+	match value {
+		Value::String(s) => s.clone(),
+		other => other.to_string(),
+	}
+}
+
+// Variables set by `setenv`. They're kept here rather than in the process
+// environment, which can't be safely changed while other threads may be
+// reading it to spawn programs.
+type Environment = Arc<Mutex<HashMap<String, String>>>;
+
+async fn run(environment: &Environment, options: &Map<String, Value>, cmd: &Output, args: &[Output]) -> Output {
+	let mut command = Command::new(cmd.value());
+	command.args(args.iter().map(|arg| arg.value()));
+	command.envs(environment.lock().unwrap().iter());
+	command.stdout(Stdio::piped());
+	if let Some(cwd) = options.get("cwd") {
+		command.current_dir(json_str(cwd));
+	}
+	if let Some(Value::Object(env)) = options.get("env") {
+		for (key, value) in env {
+			command.env(key, json_str(value));
+		}
+	}
+	let stdin = options.get("stdin").map(json_str);
+	if stdin.is_some() {
+		command.stdin(Stdio::piped());
+	}
+	let mut child = match command.spawn() {
+		Ok(child) => child,
+		Err(err) => return Output::new_falsy_with(err.to_string().into()),
+	};
+	if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+		// Feed stdin in the background, so a child producing a lot of output
+		// before reading all of its input can't deadlock us.
+		tokio::spawn(async move {
+			let _ = pipe.write_all(input.as_bytes()).await;
+		});
+	}
+	match child.wait_with_output().await {
+		Ok(output) => {
+			let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+			// Processes killed by a signal have no exit code.
+			let code = output.status.code().unwrap_or(-1);
+			Output::new(stdout.into(), code.into())
+		}
+		Err(err) => Output::new_falsy_with(err.to_string().into()),
+	}
+}
+
+async fn exec(environment: Environment, _: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	match args.as_slice() {
+		[cmd, args @ ..] => Ok(run(&environment, &Map::new(), cmd, args).await),
+		_ => Err(ExecutionError::InternalError),
+	}
+}
+
+async fn exec_with(environment: Environment, _: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (options, cmd, args) = match args.as_slice() {
+		[options, cmd, args @ ..] => (options, cmd, args),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let Ok(options) = serde_json::from_str::<Map<String, Value>>(options.value()) else {
+		return Ok(Output::new_falsy());
+	};
+	Ok(run(&environment, &options, cmd, args).await)
+}
+
+async fn env(environment: Environment, _: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let name = match args.as_slice() {
+		[name] => name,
+		_ => return Err(ExecutionError::InternalError),
+	};
+	if let Some(value) = environment.lock().unwrap().get(name.value()) {
+		return Ok(Output::new_truthy_with(value.clone().into()));
+	}
+	Ok(match std::env::var(name.value()) {
+		Ok(value) => Output::new_truthy_with(value.into()),
+		Err(_) => Output::new_falsy(),
+	})
+}
+
+async fn setenv(environment: Environment, _: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (name, value) = match args.as_slice() {
+		[name, value] => (name, value),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let name = name.value();
+	if name.is_empty() || name.contains(['=', '\0']) || value.value().contains('\0') {
+		return Ok(Output::new_falsy());
+	}
+	environment.lock().unwrap().insert(name.to_owned(), value.value().to_owned());
+	Ok(Output::new_truthy())
+}
+
+pub fn build() -> FunctionLibrary {
+	let mut library = FunctionLibrary::new();
+	let environment = Environment::default();
+	builtin_state!(library, exec, environment.clone(), "cmd", "%args");
+	builtin_state!(library, exec_with, environment.clone(), "options", "cmd", "%args");
+	builtin_state!(library, env, environment.clone(), "name");
+	builtin_state!(library, setenv, environment, "name", "value");
+	library
+}
@@ -1,9 +1,14 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::Arc};
+
+use tokio::{
+	io::{AsyncBufReadExt, AsyncReadExt, BufReader, Stdin},
+	sync::Mutex,
+};
 
 use crate::{
-	builtin, parser,
+	builtin, builtin_state, parser,
 	runtime::{
-		ExecutionError, Runtime, functions::FunctionLibrary, output::{Output, join_outputs}, scope::ScopeStack
+		ExecutionError, Runtime, call_function, functions::FunctionLibrary, output::{Output, join_outputs}, scope::ScopeStack
 	},
 };
 
@@ -68,16 +73,14 @@ async fn eval<'env, 'stack>(fl: &FunctionLibrary, stack: &'stack mut ScopeStack<
 			match program {
 				Ok(program) => {
 					let mut runtime = Runtime::new();
-					match runtime.library.merge(fl.clone()) {
-						Ok(_) => (),
+					runtime.library = fl.clone();
+					let executions = match runtime.load(program, None) {
+						Ok(executions) => executions,
 						Err(err) => return Ok(Output::new_falsy_with(err.to_string().into())),
 					};
-					match runtime.library.add_scripts(program.functions) {
-						Ok(_) => (),
-						Err(err) => return Ok(Output::new_falsy_with(err.to_string().into())),
-					};
-					match runtime.execute_scoped(stack, &program.executions).await {
+					match runtime.execute_scoped(stack, &executions).await {
 						Ok(output) => Ok(output),
+						Err(ExecutionError::Exit(output)) => Err(ExecutionError::Exit(output)),
 						Err(err) => Ok(Output::new_falsy_with(err.to_string().into())),
 					}
 				}
@@ -88,6 +91,14 @@ async fn eval<'env, 'stack>(fl: &FunctionLibrary, stack: &'stack mut ScopeStack<
 	}
 }
 
+async fn call<'env, 'stack>(fl: &FunctionLibrary, stack: &'stack mut ScopeStack<'env>, mut args: Vec<Output>) -> Result<Output, ExecutionError> {
+	if args.is_empty() {
+		return Err(ExecutionError::InternalError);
+	}
+	let handle = args.remove(0);
+	call_function(fl, stack, handle.value(), args).await
+}
+
 async fn panic(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
 	match args.as_slice() {
 		[value] => {
@@ -97,6 +108,44 @@ async fn panic(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -
 	}
 }
 
+async fn exit(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let code = match args.as_slice() {
+		[] => 0,
+		[code] => code.try_into().unwrap_or(1),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Err(ExecutionError::Exit(Output::new("".into(), code)))
+}
+
+// Shared by every builtin that reads from standard input, so that none of
+// them loses what the others have already buffered.
+type Input = Arc<Mutex<BufReader<Stdin>>>;
+
+async fn stdin(input: Input, _: &FunctionLibrary, _: &mut ScopeStack<'_>, _: Vec<Output>) -> Result<Output, ExecutionError> {
+	let mut content = String::new();
+	match input.lock().await.read_to_string(&mut content).await {
+		Ok(_) => Ok(Output::new_truthy_with(content.into())),
+		Err(err) => Ok(Output::new_falsy_with(err.to_string().into())),
+	}
+}
+
+async fn readln(input: Input, _: &FunctionLibrary, _: &mut ScopeStack<'_>, _: Vec<Output>) -> Result<Output, ExecutionError> {
+	let mut line = String::new();
+	match input.lock().await.read_line(&mut line).await {
+		Ok(0) => Ok(Output::new_falsy()),
+		Ok(_) => {
+			if line.ends_with('\n') {
+				line.pop();
+				if line.ends_with('\r') {
+					line.pop();
+				}
+			}
+			Ok(Output::new_truthy_with(line.into()))
+		}
+		Err(err) => Ok(Output::new_falsy_with(err.to_string().into())),
+	}
+}
+
 pub fn build() -> FunctionLibrary {
 	let mut library = FunctionLibrary::new();
 	builtin!(library, put, "%args");
@@ -107,7 +156,13 @@ pub fn build() -> FunctionLibrary {
 	builtin!(library, status, "value", "status");
 	builtin!(library, src, "function");
 	builtin!(library, eval, "code");
+	builtin!(library, call, "handle", "%args");
 	builtin!(library, panic,);
 	builtin!(library, panic, "message");
+	builtin!(library, exit,);
+	builtin!(library, exit, "code");
+	let input: Input = Arc::new(Mutex::new(BufReader::new(tokio::io::stdin())));
+	builtin_state!(library, stdin, input.clone());
+	builtin_state!(library, readln, input);
 	library
 }
@@ -1,9 +1,29 @@
 use crate::{
-	builtin,
+	builtin, builtin_alias,
 	runtime::{
-		ExecutionError, functions::FunctionLibrary, output::{Output, join_outputs}, scope::ScopeStack
+		ExecutionError, functions::FunctionLibrary, output::{Output, encode_list, join_outputs}, scope::ScopeStack
 	},
 };
+use serde_json::{Map, Value};
+
+// The longest string `repeat` and padding will build, in bytes and in
+// characters respectively, so a typo in a count can't exhaust memory.
+const MAX_LENGTH: usize = 1 << 24;
+
+// Positions and lengths count characters rather than bytes, so they can't
+// land in the middle of one.
+fn to_index(arg: &Output) -> Option<usize> {
+	let index: i64 = arg.try_into().ok()?;
+	usize::try_from(index).ok()
+}
+
+fn truthy_if(condition: bool) -> Output {
+	if condition {
+		Output::new_truthy()
+	} else {
+		Output::new_falsy()
+	}
+}
 
 async fn upper(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
 	let mut out = join_outputs(args.iter());
@@ -17,24 +37,24 @@ async fn lower(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -
 	Ok(out)
 }
 
-async fn replace(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+async fn replace(fl: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
 	let (target, from, to) = match args.as_slice() {
 		[out, from, to] => (out, from, to),
 		_ => return Err(ExecutionError::InternalError),
 	};
-	let Ok(regex) = regex::Regex::new(from.value()) else {
+	let Ok(regex) = fl.regexes().get(from.value()) else {
 		return Ok(Output::new_falsy());
 	};
 	let replaced = regex.replace_all(target.value(), to.value()).to_string();
 	Ok(Output::new_truthy_with(replaced.into()))
 }
 
-async fn search(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+async fn search(fl: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
 	let (target, pattern) = match args.as_slice() {
 		[target, pattern] => (target, pattern),
 		_ => return Err(ExecutionError::InternalError),
 	};
-	let Ok(reg) = regex::Regex::new(pattern.value()) else {
+	let Ok(reg) = fl.regexes().get(pattern.value()) else {
 		return Ok(Output::new_falsy());
 	};
 	let mut out = Output::new_truthy();
@@ -53,6 +73,83 @@ async fn search(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>)
 	}
 }
 
+// Joins `items` with `sep` if one was given, or as a list otherwise, since
+// matched text can contain whitespace.
+fn join_matches<'a, I: Iterator<Item = &'a str>>(items: I, sep: Option<&Output>) -> String {
+	match sep {
+		Some(sep) => items.collect::<Vec<_>>().join(sep.value()),
+		None => encode_list(items),
+	}
+}
+
+async fn regex_match(fl: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (target, pattern) = match args.as_slice() {
+		[target, pattern] => (target, pattern),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let Ok(reg) = fl.regexes().get(pattern.value()) else {
+		return Ok(Output::new_falsy());
+	};
+	Ok(match reg.find(target.value()) {
+		Some(found) => Output::new_truthy_with(found.as_str().to_owned().into()),
+		None => Output::new_falsy(),
+	})
+}
+
+async fn matches(fl: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (target, pattern, sep) = match args.as_slice() {
+		[target, pattern] => (target, pattern, None),
+		[target, pattern, sep] => (target, pattern, Some(sep)),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let Ok(reg) = fl.regexes().get(pattern.value()) else {
+		return Ok(Output::new_falsy());
+	};
+	let found: Vec<&str> = reg.find_iter(target.value()).map(|found| found.as_str()).collect();
+	if found.is_empty() {
+		return Ok(Output::new_falsy());
+	}
+	Ok(Output::new_truthy_with(join_matches(found.into_iter(), sep).into()))
+}
+
+async fn captures(fl: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (target, pattern, sep) = match args.as_slice() {
+		[target, pattern] => (target, pattern, None),
+		[target, pattern, sep] => (target, pattern, Some(sep)),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let Ok(reg) = fl.regexes().get(pattern.value()) else {
+		return Ok(Output::new_falsy());
+	};
+	let Some(caps) = reg.captures(target.value()) else {
+		return Ok(Output::new_falsy());
+	};
+	let groups = caps.iter().map(|group| group.map_or("", |group| group.as_str()));
+	Ok(Output::new_truthy_with(join_matches(groups, sep).into()))
+}
+
+async fn captures_json(fl: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (target, pattern) = match args.as_slice() {
+		[target, pattern] => (target, pattern),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let Ok(reg) = fl.regexes().get(pattern.value()) else {
+		return Ok(Output::new_falsy());
+	};
+	let Some(caps) = reg.captures(target.value()) else {
+		return Ok(Output::new_falsy());
+	};
+	let mut object = Map::new();
+	for (i, name) in reg.capture_names().enumerate() {
+		let group = caps.get(i).map_or(Value::Null, |group| Value::String(group.as_str().to_owned()));
+		object.insert(i.to_string(), group.clone());
+		if let Some(name) = name {
+			object.insert(name.to_owned(), group);
+		}
+	}
+	Ok(Output::new_truthy_with(Value::Object(object).to_string().into()))
+}
+
 async fn is_alpha(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
 	Ok(
 		if args
@@ -83,13 +180,400 @@ async fn is_alphanumeric(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<
 	)
 }
 
+async fn strlen(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	match args.as_slice() {
+		[s] => Ok(Output::new_truthy_with(s.value().chars().count().to_string().into())),
+		_ => Err(ExecutionError::InternalError),
+	}
+}
+
+async fn substr(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (s, start, len) = match args.as_slice() {
+		[s, start] => (s, to_index(start), Some(usize::MAX)),
+		[s, start, len] => (s, to_index(start), to_index(len)),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let (Some(start), Some(len)) = (start, len) else {
+		return Ok(Output::new_falsy());
+	};
+	if start > s.value().chars().count() {
+		return Ok(Output::new_falsy());
+	}
+	let sub: String = s.value().chars().skip(start).take(len).collect();
+	Ok(Output::new_truthy_with(sub.into()))
+}
+
+async fn char_at(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (s, i) = match args.as_slice() {
+		[s, i] => (s, to_index(i)),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(match i.and_then(|i| s.value().chars().nth(i)) {
+		Some(c) => Output::new_truthy_with(c.to_string().into()),
+		None => Output::new_falsy(),
+	})
+}
+
+async fn index_of(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (s, needle) = match args.as_slice() {
+		[s, needle] => (s, needle),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(match s.value().find(needle.value()) {
+		Some(byte) => Output::new_truthy_with(s.value()[..byte].chars().count().to_string().into()),
+		None => Output::new_falsy(),
+	})
+}
+
+async fn trim(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	match args.as_slice() {
+		[s] => Ok(Output::new_truthy_with(s.value().trim().to_owned().into())),
+		_ => Err(ExecutionError::InternalError),
+	}
+}
+
+async fn ltrim(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	match args.as_slice() {
+		[s] => Ok(Output::new_truthy_with(s.value().trim_start().to_owned().into())),
+		_ => Err(ExecutionError::InternalError),
+	}
+}
+
+async fn rtrim(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	match args.as_slice() {
+		[s] => Ok(Output::new_truthy_with(s.value().trim_end().to_owned().into())),
+		_ => Err(ExecutionError::InternalError),
+	}
+}
+
+async fn starts_with(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	match args.as_slice() {
+		[s, prefix] => Ok(truthy_if(s.value().starts_with(prefix.value()))),
+		_ => Err(ExecutionError::InternalError),
+	}
+}
+
+async fn ends_with(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	match args.as_slice() {
+		[s, suffix] => Ok(truthy_if(s.value().ends_with(suffix.value()))),
+		_ => Err(ExecutionError::InternalError),
+	}
+}
+
+async fn contains(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	match args.as_slice() {
+		[s, needle] => Ok(truthy_if(s.value().contains(needle.value()))),
+		_ => Err(ExecutionError::InternalError),
+	}
+}
+
+async fn repeat(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (s, n) = match args.as_slice() {
+		[s, n] => (s, to_index(n)),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let fits = |n: &usize| s.value().len().checked_mul(*n).is_some_and(|len| len <= MAX_LENGTH);
+	Ok(match n.filter(fits) {
+		Some(n) => Output::new_truthy_with(s.value().repeat(n).into()),
+		None => Output::new_falsy(),
+	})
+}
+
+async fn reverse(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	match args.as_slice() {
+		[s] => Ok(Output::new_truthy_with(s.value().chars().rev().collect::<String>().into())),
+		_ => Err(ExecutionError::InternalError),
+	}
+}
+
+// Pads `s` with `fill` up to `width` characters, on the left or on the right.
+fn pad(args: &[Output], left: bool) -> Result<Output, ExecutionError> {
+	let (s, width, fill) = match args {
+		[s, width] => (s, to_index(width), " "),
+		[s, width, fill] => (s, to_index(width), fill.value()),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	let mut fill_chars = fill.chars();
+	let width = width.filter(|width| *width <= MAX_LENGTH);
+	let (Some(width), Some(fill), None) = (width, fill_chars.next(), fill_chars.next()) else {
+		return Ok(Output::new_falsy());
+	};
+	let padding: String = std::iter::repeat_n(fill, width.saturating_sub(s.value().chars().count())).collect();
+	let padded = if left {
+		padding + s.value()
+	} else {
+		s.value().to_owned() + &padding
+	};
+	Ok(Output::new_truthy_with(padded.into()))
+}
+
+async fn pad_left(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	pad(&args, true)
+}
+
+async fn pad_right(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	pad(&args, false)
+}
+
+async fn split(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (s, separator, i) = match args.as_slice() {
+		[s, separator, i] => (s, separator, to_index(i)),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(match i.and_then(|i| s.split_iter(Some(separator)).nth(i)) {
+		Some(part) => Output::new_truthy_with(part.to_owned().into()),
+		None => Output::new_falsy(),
+	})
+}
+
+// A placeholder's format spec, as in `{:>8}` or `{:08.2}`. It follows the
+// same layout as Rust's `format!`: fill and alignment, sign, zero padding,
+// width, precision and finally the type.
+#[derive(Default)]
+struct FormatSpec {
+	fill: Option<char>,
+	align: Option<char>,
+	sign: bool,
+	zero: bool,
+	width: usize,
+	precision: Option<usize>,
+	kind: Option<char>,
+}
+
+impl FormatSpec {
+	fn parse(spec: &str) -> Option<FormatSpec> {
+		let mut parsed = FormatSpec::default();
+		let mut chars = spec.chars().peekable();
+		let mut lookahead = spec.chars().skip(1);
+		match (spec.chars().next(), lookahead.next()) {
+			(Some(fill), Some(align @ ('<' | '^' | '>'))) => {
+				parsed.fill = Some(fill);
+				parsed.align = Some(align);
+				chars.nth(1);
+			}
+			(Some(align @ ('<' | '^' | '>')), _) => {
+				parsed.align = Some(align);
+				chars.next();
+			}
+			_ => {}
+		}
+		parsed.sign = chars.next_if_eq(&'+').is_some();
+		parsed.zero = chars.next_if_eq(&'0').is_some();
+		let mut width = String::new();
+		while let Some(digit) = chars.next_if(|c| c.is_ascii_digit()) {
+			width.push(digit);
+		}
+		parsed.width = if width.is_empty() { 0 } else { width.parse().ok()? };
+		if parsed.width > MAX_LENGTH {
+			return None;
+		}
+		if chars.next_if_eq(&'.').is_some() {
+			let mut precision = String::new();
+			while let Some(digit) = chars.next_if(|c| c.is_ascii_digit()) {
+				precision.push(digit);
+			}
+			parsed.precision = Some(precision.parse().ok().filter(|precision| *precision <= MAX_LENGTH)?);
+		}
+		parsed.kind = chars.next_if(|c| matches!(c, 'x' | 'X' | 'o' | 'b' | 'e'));
+		match chars.next() {
+			Some(_) => None,
+			None => Some(parsed),
+		}
+	}
+
+	fn apply(&self, arg: &str) -> Option<String> {
+		let number = arg.trim();
+		let (mut body, numeric) = match (self.kind, self.precision) {
+			(Some(kind @ ('x' | 'X' | 'o' | 'b')), _) => {
+				let n: i64 = number.parse().ok()?;
+				let sign = if n < 0 { "-" } else { "" };
+				let n = n.unsigned_abs();
+				let digits = match kind {
+					'x' => format!("{sign}{n:x}"),
+					'X' => format!("{sign}{n:X}"),
+					'o' => format!("{sign}{n:o}"),
+					_ => format!("{sign}{n:b}"),
+				};
+				(digits, true)
+			}
+			(Some(_), Some(precision)) => (format!("{:.*e}", precision, number.parse::<f64>().ok()?), true),
+			(Some(_), None) => (format!("{:e}", number.parse::<f64>().ok()?), true),
+			// As in Rust, a precision cuts text that isn't a number short.
+			(None, Some(precision)) => match number.parse::<f64>() {
+				Ok(n) => (format!("{:.*}", precision, n), true),
+				Err(_) => (arg.chars().take(precision).collect(), false),
+			},
+			(None, None) if self.sign || self.zero => {
+				number.parse::<f64>().ok()?;
+				(number.to_owned(), true)
+			}
+			(None, None) => (arg.to_owned(), false),
+		};
+		if self.sign && numeric && !body.starts_with('-') {
+			body.insert(0, '+');
+		}
+		let padding = self.width.saturating_sub(body.chars().count());
+		if padding == 0 {
+			return Some(body);
+		}
+		if self.zero && numeric && self.align.is_none() {
+			let at = if body.starts_with(['+', '-']) { 1 } else { 0 };
+			body.insert_str(at, &"0".repeat(padding));
+			return Some(body);
+		}
+		let fill = self.fill.unwrap_or(' ');
+		let (left, right) = match self.align {
+			Some('<') => (0, padding),
+			Some('^') => (padding / 2, padding - padding / 2),
+			Some(_) => (padding, 0),
+			None if numeric => (padding, 0),
+			None => (0, padding),
+		};
+		let left: String = std::iter::repeat_n(fill, left).collect();
+		let right: String = std::iter::repeat_n(fill, right).collect();
+		Some(left + &body + &right)
+	}
+}
+
+// Fills the `{}` placeholders of `template` with `args`, in order or by index
+// as in `{1}`. Outputs `None` if the template is malformed or an argument
+// doesn't fit its placeholder.
+fn format_template(template: &str, args: &[Output]) -> Option<String> {
+	let mut out = String::new();
+	let mut chars = template.chars();
+	let mut next_arg = 0;
+	while let Some(c) = chars.next() {
+		match c {
+			'{' if chars.as_str().starts_with('{') => {
+				chars.next();
+				out.push('{');
+			}
+			'}' if chars.as_str().starts_with('}') => {
+				chars.next();
+				out.push('}');
+			}
+			'}' => return None,
+			'{' => {
+				let (placeholder, rest) = chars.as_str().split_once('}')?;
+				chars = rest.chars();
+				let (index, spec) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+				let index = if index.is_empty() {
+					next_arg += 1;
+					next_arg - 1
+				} else {
+					index.parse().ok()?
+				};
+				out.push_str(&FormatSpec::parse(spec)?.apply(args.get(index)?.value())?);
+			}
+			c => out.push(c),
+		}
+	}
+	Some(out)
+}
+
+async fn format(_: &FunctionLibrary, _: &mut ScopeStack<'_>, args: Vec<Output>) -> Result<Output, ExecutionError> {
+	let (template, args) = match args.as_slice() {
+		[template, args @ ..] => (template, args),
+		_ => return Err(ExecutionError::InternalError),
+	};
+	Ok(match format_template(template.value(), args) {
+		Some(formatted) => Output::new_truthy_with(formatted.into()),
+		None => Output::new_falsy(),
+	})
+}
+
 pub fn build() -> FunctionLibrary {
 	let mut library = FunctionLibrary::new();
 	builtin!(library, upper, "%args");
 	builtin!(library, lower, "%args");
 	builtin!(library, replace, "target", "from", "to");
 	builtin!(library, search, "target", "pattern");
+	builtin_alias!(library, regex_match, "match", "s", "pattern");
+	builtin!(library, matches, "s", "pattern");
+	builtin!(library, matches, "s", "pattern", "sep");
+	builtin!(library, captures, "s", "pattern");
+	builtin!(library, captures, "s", "pattern", "sep");
+	builtin!(library, captures_json, "s", "pattern");
 	builtin!(library, is_alpha, "%args");
 	builtin!(library, is_alphanumeric, "%args");
+	builtin!(library, strlen, "s");
+	builtin!(library, substr, "s", "start");
+	builtin!(library, substr, "s", "start", "len");
+	builtin!(library, char_at, "s", "i");
+	builtin!(library, index_of, "s", "needle");
+	builtin!(library, trim, "s");
+	builtin!(library, ltrim, "s");
+	builtin!(library, rtrim, "s");
+	builtin!(library, starts_with, "s", "prefix");
+	builtin!(library, ends_with, "s", "suffix");
+	builtin!(library, contains, "s", "needle");
+	builtin!(library, repeat, "s", "n");
+	builtin!(library, reverse, "s");
+	builtin!(library, pad_left, "s", "width");
+	builtin!(library, pad_left, "s", "width", "fill");
+	builtin!(library, pad_right, "s", "width");
+	builtin!(library, pad_right, "s", "width", "fill");
+	builtin!(library, split, "s", "sep", "i");
+	builtin!(library, format, "template", "%args");
 	library
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn apply(spec: &str, arg: &str) -> Option<String> {
+		FormatSpec::parse(spec)?.apply(arg)
+	}
+
+	#[test]
+	fn format_spec_aligns_like_rust() {
+		assert_eq!(apply(">6", "ab").as_deref(), Some("    ab"));
+		assert_eq!(apply("<6", "ab").as_deref(), Some("ab    "));
+		assert_eq!(apply("*^7", "ab").as_deref(), Some("**ab***"));
+		assert_eq!(apply("5", "42").as_deref(), Some("42   "));
+		assert_eq!(apply("5.1", "42").as_deref(), Some(" 42.0"));
+		assert_eq!(apply("2", "abcd").as_deref(), Some("abcd"));
+	}
+
+	#[test]
+	fn format_spec_formats_numbers() {
+		assert_eq!(apply("08.2", "3.14159").as_deref(), Some("00003.14"));
+		assert_eq!(apply("+", "5").as_deref(), Some("+5"));
+		assert_eq!(apply("+05", "-5").as_deref(), Some("-0005"));
+		assert_eq!(apply("x", "255").as_deref(), Some("ff"));
+		assert_eq!(apply("X", "-255").as_deref(), Some("-FF"));
+		assert_eq!(apply("b", "5").as_deref(), Some("101"));
+		assert_eq!(apply(".2e", "1500").as_deref(), Some("1.50e3"));
+		assert_eq!(apply("x", "1.5"), None);
+		assert_eq!(apply("+", "ab"), None);
+	}
+
+	#[test]
+	fn format_spec_truncates_text_by_precision() {
+		assert_eq!(apply(".2", "hello").as_deref(), Some("he"));
+		assert_eq!(apply(">4.2", "hello").as_deref(), Some("  he"));
+		assert_eq!(apply(".9", "hi").as_deref(), Some("hi"));
+	}
+
+	#[test]
+	fn format_spec_rejects_bad_specs() {
+		assert!(FormatSpec::parse("abc").is_none());
+		assert!(FormatSpec::parse(">5q").is_none());
+		assert!(FormatSpec::parse("99999999999999").is_none());
+		assert!(FormatSpec::parse(".99999999999999").is_none());
+		assert!(FormatSpec::parse("99999999999999999999999").is_none());
+	}
+
+	#[test]
+	fn format_template_fills_placeholders() {
+		let args = [Output::new_truthy_with("a".into()), Output::new_truthy_with("b".into())];
+		assert_eq!(format_template("{} {}", &args).as_deref(), Some("a b"));
+		assert_eq!(format_template("{1} {0}", &args).as_deref(), Some("b a"));
+		assert_eq!(format_template("{{{}}}", &args).as_deref(), Some("{a}"));
+		assert_eq!(format_template("{:>3}|", &args).as_deref(), Some("  a|"));
+		assert_eq!(format_template("{2}", &args), None);
+		assert_eq!(format_template("{", &args), None);
+		assert_eq!(format_template("}", &args), None);
+	}
+}
@@ -1,22 +1,45 @@
-use std::{fmt::Display, fs, path::PathBuf, process::ExitCode};
+mod repl;
+
+use std::{
+	fmt::Display,
+	fs,
+	io::{self, Read},
+	path::PathBuf,
+	process::ExitCode,
+};
 
 use clap::Parser;
 use dog3::{
 	builtin,
-	parser::{parse, Rule},
-	runtime::{functions::RegisterError, Runtime},
+	parser::{parse_file, Rule},
+	runtime::{
+		functions::RegisterError, modules::ModuleError, output::Output, ExecutionError, Runtime,
+	},
 };
 
 #[derive(Parser, Debug)]
 struct Args {
+	/// Script files to run, or `-` to read one from standard input. Starts
+	/// an interactive session when there's nothing to run.
 	inputs: Vec<PathBuf>,
+	/// Code to run after the script files.
+	#[arg(short = 'e', value_name = "CODE")]
+	expressions: Vec<String>,
+	/// Write out what each top-level statement outputs as soon as it's done,
+	/// instead of all at once when the script ends.
+	#[arg(short = 's', long)]
+	stream: bool,
+	/// Arguments passed on to the script, as `$1`, `$2`... and `$argv`.
+	#[arg(last = true)]
+	args: Vec<String>,
 }
 
 #[derive(Debug)]
 enum Error {
 	IO(std::io::Error),
-	Syntax(pest::error::Error<Rule>),
+	Syntax(Box<pest::error::Error<Rule>>),
 	Library(RegisterError),
+	Module(ModuleError),
 }
 
 impl Display for Error {
@@ -31,6 +54,7 @@ impl Display for Error {
 			Error::Library(err) => {
 				write!(f, "error: Failed to load standard libraries.\n{}", err)
 			}
+			Error::Module(err) => write!(f, "{}", err),
 		}
 	}
 }
@@ -41,8 +65,8 @@ impl From<std::io::Error> for Error {
 	}
 }
 
-impl From<pest::error::Error<Rule>> for Error {
-	fn from(value: pest::error::Error<Rule>) -> Self {
+impl From<Box<pest::error::Error<Rule>>> for Error {
+	fn from(value: Box<pest::error::Error<Rule>>) -> Self {
 		Self::Syntax(value)
 	}
 }
@@ -53,42 +77,109 @@ impl From<RegisterError> for Error {
 	}
 }
 
+impl From<ModuleError> for Error {
+	fn from(value: ModuleError) -> Self {
+		Self::Module(value)
+	}
+}
+
 fn register_libraries(runtime: &mut Runtime) -> Result<String, RegisterError> {
 	runtime.library.merge(builtin::std::build())?;
 	runtime.library.merge(builtin::iter::build())?;
+	runtime.library.merge(builtin::map::build())?;
 	runtime.library.merge(builtin::math::build())?;
 	runtime.library.merge(builtin::logic::build())?;
 	runtime.library.merge(builtin::net::build())?;
 	runtime.library.merge(builtin::str::build())?;
+	runtime.library.merge(builtin::fs::build())?;
+	runtime.library.merge(builtin::process::build())?;
 	runtime.library.merge(builtin::json::build())
 }
 
-async fn run() -> Result<(), Error> {
+fn declare_args(runtime: &mut Runtime, script: String, args: &[String]) {
+	let mut declare = |name: &str, value: String| {
+		runtime
+			.globals
+			.insert(name.to_owned(), Output::new_truthy_with(value.into()));
+	};
+	declare("0", script);
+	declare("argc", args.len().to_string());
+	declare("argv", args.join(" "));
+	for (i, arg) in args.iter().enumerate() {
+		declare(&(i + 1).to_string(), arg.clone());
+	}
+}
+
+// Exit code used when the script is aborted by a runtime error, so callers
+// can tell it apart from a script that simply ended on a falsy status.
+const RUNTIME_ERROR: u8 = 70;
+
+// Status codes that don't fit in an exit code are reported as a plain failure
+// rather than truncated, which could turn them into a success.
+fn exit_code(code: i64) -> ExitCode {
+	ExitCode::from(u8::try_from(code).unwrap_or(1))
+}
+
+async fn run() -> Result<ExitCode, Error> {
 	let args = Args::parse();
-	let mut inputs = vec![];
+	let mut runtime = Runtime::new();
+	register_libraries(&mut runtime)?;
+	let script = match (args.inputs.first(), args.expressions.is_empty()) {
+		(Some(path), _) => path.to_string_lossy().into_owned(),
+		(None, false) => "-e".to_owned(),
+		(None, true) => String::new(),
+	};
+	declare_args(&mut runtime, script, &args.args);
+	if args.inputs.is_empty() && args.expressions.is_empty() {
+		return Ok(repl::run(&mut runtime).await?);
+	}
+	let mut executions = vec![];
 	for path in args.inputs {
-		match fs::read_to_string(path) {
-			Ok(content) => inputs.push(content),
+		if path.as_os_str() == "-" {
+			let mut content = String::new();
+			io::stdin().read_to_string(&mut content)?;
+			let program = parse_file("<stdin>", &content)?;
+			executions.extend(runtime.load(program, None)?);
+			continue;
+		}
+		let content = match fs::read_to_string(&path) {
+			Ok(content) => content,
 			Err(err) => {
 				return Err(Error::IO(err));
 			}
-		}
+		};
+		let program = parse_file(&path.to_string_lossy(), &content)?;
+		executions.extend(runtime.load(program, Some(&path))?);
 	}
-	let mut runtime = Runtime::new();
-	register_libraries(&mut runtime)?;
-	let program = parse(&inputs.join("\n"))?;
-	runtime.library.add_scripts(program.functions)?;
-	match runtime.execute(&program.executions).await {
-		Ok(output) => print!("{}", output.value()),
-		Err(err) => eprintln!("{}", err),
+	for code in args.expressions.iter() {
+		let program = parse_file("-e", code)?;
+		executions.extend(runtime.load(program, None)?);
+	}
+	if args.stream {
+		return match runtime.stream(&executions, &mut io::stdout()).await {
+			Ok(code) => Ok(exit_code(code)),
+			Err(err) => {
+				eprintln!("{}", err);
+				Ok(ExitCode::from(RUNTIME_ERROR))
+			}
+		};
+	}
+	match runtime.execute(&executions).await {
+		Ok(output) | Err(ExecutionError::Exit(output)) => {
+			print!("{}", output.value());
+			Ok(exit_code(output.code()))
+		}
+		Err(err) => {
+			eprintln!("{}", err);
+			Ok(ExitCode::from(RUNTIME_ERROR))
+		}
 	}
-	Ok(())
 }
 
 #[tokio::main]
 async fn main() -> ExitCode {
 	match run().await {
-		Ok(_) => ExitCode::SUCCESS,
+		Ok(code) => code,
 		Err(err) => {
 			println!("{}", err);
 			ExitCode::FAILURE
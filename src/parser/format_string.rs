@@ -1,4 +1,4 @@
-use super::str_range::Range;
+use super::{span::Span, str_range::Range};
 
 #[derive(Debug, Clone)]
 pub struct FormatString {
@@ -9,7 +9,7 @@ pub struct FormatString {
 #[derive(Debug, Clone)]
 pub enum Region {
 	Raw(Range),
-	Variable(Range),
+	Variable(Range, Span),
 }
 
 pub struct FormatStringIter<'a> {
@@ -20,7 +20,7 @@ pub struct FormatStringIter<'a> {
 #[derive(Debug, Clone)]
 pub enum FormatStringPiece<'a> {
 	Raw(&'a str),
-	Variable(&'a str),
+	Variable(&'a str, &'a Span),
 }
 
 fn is_special(c: char) -> bool {
@@ -58,19 +58,21 @@ fn escape(c: char) -> char {
 	}
 }
 
-fn remove_ends<'a>(raw: &'a str) -> &'a str {
+// Strips the surrounding quotes, if any, returning how many bytes were
+// removed from the front.
+fn remove_ends<'a>(raw: &'a str) -> (&'a str, usize) {
 	match raw.chars().next() {
 		Some(c) => match c {
 			'\'' | '"' => {
 				if raw.len() < 2 {
-					""
+					("", 0)
 				} else {
-					&raw[1..raw.len() - 1]
+					(&raw[1..raw.len() - 1], 1)
 				}
 			}
-			_ => raw,
+			_ => (raw, 0),
 		},
-		None => raw,
+		None => (raw, 0),
 	}
 }
 
@@ -78,7 +80,7 @@ impl Region {
 	fn range(&self) -> &Range {
 		match self {
 			Region::Raw(r) => r,
-			Region::Variable(r) => r,
+			Region::Variable(r, _) => r,
 		}
 	}
 }
@@ -99,8 +101,21 @@ impl FormatString {
 			})],
 		}
 	}
-	pub fn parse(raw: &str, expand_variables: bool) -> FormatString {
-		let raw = remove_ends(raw);
+	// The text of this string, if it doesn't depend on any variables.
+	pub fn literal(&self) -> Option<String> {
+		let mut text = String::new();
+		for piece in self.into_iter() {
+			match piece {
+				FormatStringPiece::Raw(raw) => text.push_str(raw),
+				FormatStringPiece::Variable(..) => return None,
+			}
+		}
+		Some(text)
+	}
+	// `span` must cover exactly `raw`, and is used to locate each variable
+	// in the source.
+	pub fn parse(raw: &str, expand_variables: bool, span: &Span) -> FormatString {
+		let (raw, offset) = remove_ends(raw);
 		if !expand_variables {
 			return FormatString::raw(raw);
 		}
@@ -109,9 +124,11 @@ impl FormatString {
 			return target;
 		}
 		let mut current_region = None;
+		let mut variable_start = 0;
 		let mut iter = raw.chars();
 
 		loop {
+			let position = offset + raw.len() - iter.as_str().len();
 			let c = match iter.next() {
 				Some('\\') => match iter.next() {
 					Some(c) => Some(escape(c)),
@@ -133,8 +150,9 @@ impl FormatString {
 							range.grow(c);
 						}
 					}
-					Region::Variable(ref mut range) => {
+					Region::Variable(ref mut range, ref mut location) => {
 						if is_identifier_boundary {
+							*location = span.sub(variable_start, position);
 							target.regions.push(current_region.unwrap());
 							current_region = None
 						} else {
@@ -146,7 +164,8 @@ impl FormatString {
 			if current_region.is_none() {
 				let range = Range::first_after(target.regions.last().map(|re| re.range()), c);
 				current_region = Some(if is_raw_boundary {
-					Region::Variable(range)
+					variable_start = position;
+					Region::Variable(range, span.clone())
 				} else {
 					Region::Raw(range)
 				});
@@ -186,8 +205,8 @@ impl<'a> Iterator for FormatStringIter<'a> {
 				self.region += 1;
 				Some(match region {
 					Region::Raw(range) => FormatStringPiece::Raw(range.slice(&self.target.value)),
-					Region::Variable(range) => {
-						FormatStringPiece::Variable(range.slice(&self.target.value))
+					Region::Variable(range, span) => {
+						FormatStringPiece::Variable(range.slice(&self.target.value), span)
 					}
 				})
 			}
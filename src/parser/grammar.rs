@@ -1,17 +1,26 @@
-use super::format_string::FormatString;
+use super::{format_string::FormatString, span::Span};
 
 #[derive(Debug)]
 pub struct Program {
+	pub imports: Vec<Import>,
 	pub functions: Vec<Function>,
 	pub executions: Vec<Execution>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub struct Import {
+	pub path: String,
+	pub namespace: Option<String>,
+	pub span: Span,
+}
+
+#[derive(Debug, Clone)]
 pub struct Function {
 	pub name: String,
 	pub args: Vec<FormalParameter>,
 	pub block: Block,
 	pub script: String,
+	pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -25,6 +34,7 @@ pub enum Execution {
 	Block(Block),
 	ControlStatement(ControlStatement),
 	OpenStatement(OpenStatement),
+	Function(Function),
 }
 
 #[derive(Debug, Clone)]
@@ -35,9 +45,12 @@ pub struct Block {
 #[derive(Debug, Clone)]
 pub enum ControlStatement {
 	ForStatement(ForStatement),
+	ForMapStatement(ForMapStatement),
 	IfStatement(IfStatement),
 	IfElseStatement(IfElseStatement),
 	WhileStatement(WhileStatement),
+	MatchStatement(MatchStatement),
+	TryStatement(TryStatement),
 }
 
 #[derive(Debug, Clone)]
@@ -45,6 +58,8 @@ pub enum OpenStatement {
 	SetStmt(SetStatement),
 	ReturnStmt(ReturnStatement),
 	ClearStmt(ClearStatement),
+	BreakStmt(BreakStatement),
+	ContinueStmt(ContinueStatement),
 	CommandStmt(CommandStatement),
 }
 
@@ -53,16 +68,34 @@ pub enum Value {
 	String(FormatString),
 	Block(Box<Block>),
 	ControlStatement(Box<ControlStatement>),
+	Lambda(Box<Lambda>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Lambda {
+	pub args: Vec<FormalParameter>,
+	pub block: Block,
+	pub script: String,
+	pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct ForStatement {
 	pub variable: String,
 	pub split: Option<Value>,
+	pub encoded: bool,
 	pub list: Value,
 	pub output: Value,
 }
 
+#[derive(Debug, Clone)]
+pub struct ForMapStatement {
+	pub key: String,
+	pub value: String,
+	pub map: Value,
+	pub output: Value,
+}
+
 #[derive(Debug, Clone)]
 pub struct IfStatement {
 	pub condition: Value,
@@ -82,10 +115,38 @@ pub struct WhileStatement {
 	pub output: Value,
 }
 
+#[derive(Debug, Clone)]
+pub struct MatchStatement {
+	pub value: Value,
+	pub arms: Vec<MatchArm>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+	pub patterns: Vec<Pattern>,
+	pub output: Value,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+	Text(FormatString),
+	Regex(FormatString, Span),
+	Wildcard,
+}
+
+#[derive(Debug, Clone)]
+pub struct TryStatement {
+	pub output: Value,
+	pub error: String,
+	pub kind: Option<String>,
+	pub handler: Value,
+}
+
 #[derive(Debug, Clone)]
 pub struct SetStatement {
 	pub variable: String,
 	pub value: Value,
+	pub span: Span,
 }
 
 #[derive(Debug, Clone)]
@@ -98,10 +159,22 @@ pub struct ClearStatement {
 	pub value: Option<Value>,
 }
 
+#[derive(Debug, Clone)]
+pub struct BreakStatement {
+	pub value: Option<Value>,
+	pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContinueStatement {
+	pub span: Span,
+}
+
 #[derive(Debug, Clone)]
 pub struct CommandStatement {
 	pub name: String,
 	pub parameters: Vec<ActualParameter>,
+	pub span: Span,
 }
 
 #[derive(Debug, Clone)]
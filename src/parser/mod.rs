@@ -1,7 +1,10 @@
 pub mod format_string;
 pub mod grammar;
+pub mod span;
 mod str_range;
 
+use std::sync::Arc;
+
 use pest::error::Error;
 use pest::iterators::Pair;
 use pest::Parser;
@@ -10,6 +13,7 @@ use pest_derive::Parser;
 use crate::parser::grammar::*;
 
 use format_string::FormatString;
+use span::{Source, Span};
 
 #[derive(Parser)]
 #[grammar = "parser/grammar.pest"]
@@ -17,31 +21,34 @@ use format_string::FormatString;
 pub struct Dog3Parser;
 
 trait AST {
-	fn build(entry: Pair<'_, Rule>) -> Self;
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self;
 }
 
 impl AST for String {
-	fn build(entry: Pair<'_, Rule>) -> Self {
+	fn build(entry: Pair<'_, Rule>, _: &Arc<Source>) -> Self {
 		entry.as_span().as_str().to_owned()
 	}
 }
 
 impl AST for FormatString {
-	fn build(entry: Pair<'_, Rule>) -> Self {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
+		let span = Span::new(source, entry.as_span());
 		FormatString::parse(
 			entry.as_span().as_str(),
 			entry.into_inner().next().unwrap().as_rule() != Rule::SQuoteString,
+			&span,
 		)
 	}
 }
 
 impl AST for Value {
-	fn build(entry: Pair<'_, Rule>) -> Self {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
 		for pair in entry.into_inner() {
 			match pair.as_rule() {
-				Rule::String => return Value::String(AST::build(pair)),
-				Rule::Block => return Value::Block(Box::new(AST::build(pair))),
-				Rule::ControlStmt => return Value::ControlStatement(Box::new(AST::build(pair))),
+				Rule::String => return Value::String(AST::build(pair, source)),
+				Rule::Block => return Value::Block(Box::new(AST::build(pair, source))),
+				Rule::ControlStmt => return Value::ControlStatement(Box::new(AST::build(pair, source))),
+				Rule::Lambda => return Value::Lambda(Box::new(AST::build(pair, source))),
 				_ => unreachable!(),
 			}
 		}
@@ -50,25 +57,26 @@ impl AST for Value {
 }
 
 impl AST for ForStatement {
-	fn build(entry: Pair<'_, Rule>) -> Self {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
 		match entry.as_rule() {
-			Rule::ForStmt => {
+			Rule::ForStmt | Rule::ForListStmt => {
+				let encoded = entry.as_rule() == Rule::ForListStmt;
 				let mut variable = String::new();
 				let mut list = Value::String(FormatString::empty());
 				let mut output = Value::String(FormatString::empty());
 				let mut value_index = 0;
 				for pair in entry.into_inner() {
 					match pair.as_rule() {
-						Rule::Identifier => variable = AST::build(pair),
+						Rule::Identifier => variable = AST::build(pair, source),
 						Rule::Value => {
 							match value_index {
-								0 => list = AST::build(pair),
-								1 => output = AST::build(pair),
+								0 => list = AST::build(pair, source),
+								1 => output = AST::build(pair, source),
 								_ => unreachable!(),
 							}
 							value_index += 1
 						}
-						Rule::For | Rule::In | Rule::Split => continue,
+						Rule::For | Rule::In | Rule::List => continue,
 						_ => unreachable!(),
 					}
 				}
@@ -77,6 +85,7 @@ impl AST for ForStatement {
 					list,
 					output,
 					split: None,
+					encoded,
 				}
 			}
 			Rule::ForSplitStmt => {
@@ -87,12 +96,12 @@ impl AST for ForStatement {
 				let mut value_index = 0;
 				for pair in entry.into_inner() {
 					match pair.as_rule() {
-						Rule::Identifier => variable = AST::build(pair),
+						Rule::Identifier => variable = AST::build(pair, source),
 						Rule::Value => {
 							match value_index {
-								0 => list = AST::build(pair),
-								1 => split = AST::build(pair),
-								2 => output = AST::build(pair),
+								0 => list = AST::build(pair, source),
+								1 => split = AST::build(pair, source),
+								2 => output = AST::build(pair, source),
 								_ => unreachable!(),
 							}
 							value_index += 1
@@ -106,6 +115,7 @@ impl AST for ForStatement {
 					list,
 					output,
 					split: Some(split),
+					encoded: false,
 				}
 			}
 			_ => unreachable!(),
@@ -113,8 +123,39 @@ impl AST for ForStatement {
 	}
 }
 
+impl AST for ForMapStatement {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
+		let mut names: Vec<String> = vec![];
+		let mut map = Value::String(FormatString::empty());
+		let mut output = Value::String(FormatString::empty());
+		let mut value_index = 0;
+		for pair in entry.into_inner() {
+			match pair.as_rule() {
+				Rule::Identifier => names.push(AST::build(pair, source)),
+				Rule::Value => {
+					match value_index {
+						0 => map = AST::build(pair, source),
+						1 => output = AST::build(pair, source),
+						_ => unreachable!(),
+					}
+					value_index += 1
+				}
+				Rule::For | Rule::In | Rule::Map => continue,
+				_ => unreachable!(),
+			}
+		}
+		let mut names = names.into_iter();
+		ForMapStatement {
+			key: names.next().unwrap(),
+			value: names.next().unwrap(),
+			map,
+			output,
+		}
+	}
+}
+
 impl AST for IfStatement {
-	fn build(entry: Pair<'_, Rule>) -> Self {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
 		let mut condition = Value::String(FormatString::empty());
 		let mut output = Value::String(FormatString::empty());
 		let mut value_index = 0;
@@ -122,8 +163,8 @@ impl AST for IfStatement {
 			match pair.as_rule() {
 				Rule::Value => {
 					match value_index {
-						0 => condition = AST::build(pair),
-						1 => output = AST::build(pair),
+						0 => condition = AST::build(pair, source),
+						1 => output = AST::build(pair, source),
 						_ => unreachable!(),
 					}
 					value_index += 1
@@ -137,7 +178,7 @@ impl AST for IfStatement {
 }
 
 impl AST for IfElseStatement {
-	fn build(entry: Pair<'_, Rule>) -> Self {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
 		let mut condition = Value::String(FormatString::empty());
 		let mut output_true = Value::String(FormatString::empty());
 		let mut output_false = Value::String(FormatString::empty());
@@ -146,9 +187,9 @@ impl AST for IfElseStatement {
 			match pair.as_rule() {
 				Rule::Value => {
 					match value_index {
-						0 => condition = AST::build(pair),
-						1 => output_true = AST::build(pair),
-						2 => output_false = AST::build(pair),
+						0 => condition = AST::build(pair, source),
+						1 => output_true = AST::build(pair, source),
+						2 => output_false = AST::build(pair, source),
 						_ => unreachable!(),
 					}
 					value_index += 1
@@ -166,7 +207,7 @@ impl AST for IfElseStatement {
 }
 
 impl AST for WhileStatement {
-	fn build(entry: Pair<'_, Rule>) -> Self {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
 		let mut condition = Value::String(FormatString::empty());
 		let mut output = Value::String(FormatString::empty());
 		let mut value_index = 0;
@@ -174,8 +215,8 @@ impl AST for WhileStatement {
 			match pair.as_rule() {
 				Rule::Value => {
 					match value_index {
-						0 => condition = AST::build(pair),
-						1 => output = AST::build(pair),
+						0 => condition = AST::build(pair, source),
+						1 => output = AST::build(pair, source),
 						_ => unreachable!(),
 					}
 					value_index += 1
@@ -188,15 +229,105 @@ impl AST for WhileStatement {
 	}
 }
 
+impl AST for Pattern {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
+		for pair in entry.into_inner() {
+			match pair.as_rule() {
+				Rule::Wildcard => return Pattern::Wildcard,
+				Rule::String => return Pattern::Text(AST::build(pair, source)),
+				Rule::RegexPattern => {
+					let span = Span::new(source, pair.as_span());
+					for pair in pair.into_inner() {
+						match pair.as_rule() {
+							Rule::String => return Pattern::Regex(AST::build(pair, source), span),
+							Rule::Tilde => continue,
+							_ => unreachable!(),
+						}
+					}
+				}
+				_ => unreachable!(),
+			}
+		}
+		unreachable!()
+	}
+}
+
+impl AST for MatchArm {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
+		let mut patterns: Vec<Pattern> = vec![];
+		let mut output = Value::String(FormatString::empty());
+		for pair in entry.into_inner() {
+			match pair.as_rule() {
+				Rule::MatchPattern => patterns.push(AST::build(pair, source)),
+				Rule::Value => output = AST::build(pair, source),
+				Rule::Pipe | Rule::Arrow => continue,
+				_ => unreachable!(),
+			}
+		}
+		MatchArm { patterns, output }
+	}
+}
+
+impl AST for MatchStatement {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
+		let mut value = Value::String(FormatString::empty());
+		let mut arms: Vec<MatchArm> = vec![];
+		for pair in entry.into_inner() {
+			match pair.as_rule() {
+				Rule::Value => value = AST::build(pair, source),
+				Rule::MatchArm => arms.push(AST::build(pair, source)),
+				Rule::Match | Rule::LCurly | Rule::RCurly | Rule::Comma => continue,
+				_ => unreachable!(),
+			}
+		}
+		MatchStatement { value, arms }
+	}
+}
+
+impl AST for TryStatement {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
+		let mut output = Value::String(FormatString::empty());
+		let mut handler = Value::String(FormatString::empty());
+		let mut names: Vec<String> = vec![];
+		let mut value_index = 0;
+		for pair in entry.into_inner() {
+			match pair.as_rule() {
+				Rule::Value => {
+					match value_index {
+						0 => output = AST::build(pair, source),
+						1 => handler = AST::build(pair, source),
+						_ => unreachable!(),
+					}
+					value_index += 1
+				}
+				Rule::Identifier => names.push(AST::build(pair, source)),
+				Rule::Try | Rule::Catch => continue,
+				_ => unreachable!(),
+			}
+		}
+		let mut names = names.into_iter();
+		TryStatement {
+			output,
+			error: names.next().unwrap(),
+			kind: names.next(),
+			handler,
+		}
+	}
+}
+
 impl AST for ControlStatement {
-	fn build(entry: Pair<'_, Rule>) -> Self {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
 		for pair in entry.into_inner() {
 			match pair.as_rule() {
-				Rule::ForStmt => return ControlStatement::ForStatement(AST::build(pair)),
-				Rule::ForSplitStmt => return ControlStatement::ForStatement(AST::build(pair)),
-				Rule::IfStmt => return ControlStatement::IfStatement(AST::build(pair)),
-				Rule::IfElseStmt => return ControlStatement::IfElseStatement(AST::build(pair)),
-				Rule::WhileStmt => return ControlStatement::WhileStatement(AST::build(pair)),
+				Rule::ForStmt => return ControlStatement::ForStatement(AST::build(pair, source)),
+				Rule::ForSplitStmt => return ControlStatement::ForStatement(AST::build(pair, source)),
+				Rule::ForListStmt => return ControlStatement::ForStatement(AST::build(pair, source)),
+				Rule::ForMapStmt => return ControlStatement::ForMapStatement(AST::build(pair, source)),
+				Rule::IfStmt => return ControlStatement::IfStatement(AST::build(pair, source)),
+				Rule::IfElseStmt => return ControlStatement::IfElseStatement(AST::build(pair, source)),
+				Rule::WhileStmt => return ControlStatement::WhileStatement(AST::build(pair, source)),
+				Rule::MatchStmt => return ControlStatement::MatchStatement(AST::build(pair, source)),
+				Rule::TryStmt => return ControlStatement::TryStatement(AST::build(pair, source)),
 				_ => unreachable!(),
 			}
 		}
@@ -205,17 +336,18 @@ impl AST for ControlStatement {
 }
 
 impl AST for CommandStatement {
-	fn build(entry: Pair<'_, Rule>) -> Self {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
 		let mut name = String::new();
 		let mut parameters: Vec<ActualParameter> = vec![];
+		let span = Span::new(source, entry.as_span());
 		for pair in entry.into_inner() {
 			match pair.as_rule() {
-				Rule::Identifier => name = AST::build(pair),
+				Rule::Identifier => name = AST::build(pair, source),
 				Rule::CommandArgs => {
 					for pair in pair.into_inner() {
 						match pair.as_rule() {
 							Rule::Value => parameters.push(ActualParameter {
-								value: AST::build(pair),
+								value: AST::build(pair, source),
 							}),
 							_ => unreachable!(),
 						}
@@ -224,33 +356,42 @@ impl AST for CommandStatement {
 				_ => unreachable!(),
 			}
 		}
-		CommandStatement { name, parameters }
+		CommandStatement {
+			name,
+			parameters,
+			span,
+		}
 	}
 }
 
 impl AST for SetStatement {
-	fn build(entry: Pair<'_, Rule>) -> Self {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
 		let mut variable = String::new();
 		let mut value = Value::String(FormatString::empty());
+		let span = Span::new(source, entry.as_span());
 		for pair in entry.into_inner() {
 			match pair.as_rule() {
-				Rule::Identifier => variable = AST::build(pair),
-				Rule::Value => value = AST::build(pair),
+				Rule::Identifier => variable = AST::build(pair, source),
+				Rule::Value => value = AST::build(pair, source),
 				Rule::Equals => continue,
 				_ => unreachable!(),
 			}
 		}
-		SetStatement { variable, value }
+		SetStatement {
+			variable,
+			value,
+			span,
+		}
 	}
 }
 
 impl AST for ClearStatement {
-	fn build(entry: Pair<'_, Rule>) -> Self {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
 		for pair in entry.into_inner() {
 			match pair.as_rule() {
 				Rule::Value => {
 					return ClearStatement {
-						value: Some(AST::build(pair)),
+						value: Some(AST::build(pair, source)),
 					}
 				}
 				Rule::Clear => continue,
@@ -262,12 +403,12 @@ impl AST for ClearStatement {
 }
 
 impl AST for ReturnStatement {
-	fn build(entry: Pair<'_, Rule>) -> Self {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
 		for pair in entry.into_inner() {
 			match pair.as_rule() {
 				Rule::Value => {
 					return ReturnStatement {
-						value: Some(AST::build(pair)),
+						value: Some(AST::build(pair, source)),
 					}
 				}
 				Rule::Return => continue,
@@ -278,14 +419,43 @@ impl AST for ReturnStatement {
 	}
 }
 
+impl AST for BreakStatement {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
+		let span = Span::new(source, entry.as_span());
+		for pair in entry.into_inner() {
+			match pair.as_rule() {
+				Rule::Value => {
+					return BreakStatement {
+						value: Some(AST::build(pair, source)),
+						span,
+					}
+				}
+				Rule::Break => continue,
+				_ => unreachable!(),
+			}
+		}
+		BreakStatement { value: None, span }
+	}
+}
+
+impl AST for ContinueStatement {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
+		ContinueStatement {
+			span: Span::new(source, entry.as_span()),
+		}
+	}
+}
+
 impl AST for OpenStatement {
-	fn build(entry: Pair<'_, Rule>) -> Self {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
 		for pair in entry.into_inner() {
 			match pair.as_rule() {
-				Rule::CommandStmt => return OpenStatement::CommandStmt(AST::build(pair)),
-				Rule::SetStmt => return OpenStatement::SetStmt(AST::build(pair)),
-				Rule::ClearStmt => return OpenStatement::ClearStmt(AST::build(pair)),
-				Rule::ReturnStmt => return OpenStatement::ReturnStmt(AST::build(pair)),
+				Rule::CommandStmt => return OpenStatement::CommandStmt(AST::build(pair, source)),
+				Rule::SetStmt => return OpenStatement::SetStmt(AST::build(pair, source)),
+				Rule::ClearStmt => return OpenStatement::ClearStmt(AST::build(pair, source)),
+				Rule::ReturnStmt => return OpenStatement::ReturnStmt(AST::build(pair, source)),
+				Rule::BreakStmt => return OpenStatement::BreakStmt(AST::build(pair, source)),
+				Rule::ContinueStmt => return OpenStatement::ContinueStmt(AST::build(pair, source)),
 				_ => unreachable!(),
 			}
 		}
@@ -294,29 +464,17 @@ impl AST for OpenStatement {
 }
 
 impl AST for Function {
-	fn build(entry: Pair<'_, Rule>) -> Self {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
 		let mut name = String::new();
 		let mut args: Vec<FormalParameter> = vec![];
 		let mut block: Block = Block { executions: vec![] };
 		let def = entry.as_str().to_owned();
+		let span = Span::new(source, entry.as_span());
 		for pair in entry.into_inner() {
 			match pair.as_rule() {
-				Rule::Identifier => name = AST::build(pair),
-				Rule::FormalArgs => {
-					let mut destroy = false;
-					for pair in pair.into_inner() {
-						match pair.as_rule() {
-							Rule::Identifier => args.push(FormalParameter {
-								name: AST::build(pair),
-								vector: destroy,
-							}),
-							Rule::Destroy => destroy = true,
-							Rule::Comma => continue,
-							_ => unreachable!(),
-						}
-					}
-				}
-				Rule::Block => block = AST::build(pair),
+				Rule::Identifier => name = AST::build(pair, source),
+				Rule::FormalArgs => args = AST::build(pair, source),
+				Rule::Block => block = AST::build(pair, source),
 				Rule::Fn | Rule::RPar | Rule::LPar => continue,
 				_ => unreachable!(),
 			}
@@ -326,12 +484,76 @@ impl AST for Function {
 			args,
 			block,
 			script: def,
+			span,
+		}
+	}
+}
+
+impl AST for Vec<FormalParameter> {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
+		let mut args: Vec<FormalParameter> = vec![];
+		let mut destroy = false;
+		for pair in entry.into_inner() {
+			match pair.as_rule() {
+				Rule::Identifier => args.push(FormalParameter {
+					name: AST::build(pair, source),
+					vector: destroy,
+				}),
+				Rule::Destroy => destroy = true,
+				Rule::Comma => continue,
+				_ => unreachable!(),
+			}
+		}
+		args
+	}
+}
+
+impl AST for Lambda {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
+		let mut args: Vec<FormalParameter> = vec![];
+		let mut block: Block = Block { executions: vec![] };
+		let def = entry.as_str().to_owned();
+		let span = Span::new(source, entry.as_span());
+		for pair in entry.into_inner() {
+			match pair.as_rule() {
+				Rule::FormalArgs => args = AST::build(pair, source),
+				Rule::Block => block = AST::build(pair, source),
+				Rule::Fn | Rule::RPar | Rule::LPar => continue,
+				_ => unreachable!(),
+			}
+		}
+		Lambda {
+			args,
+			block,
+			script: def,
+			span,
+		}
+	}
+}
+
+impl AST for Import {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
+		let mut path = String::new();
+		let mut namespace = None;
+		let span = Span::new(source, entry.as_span());
+		for pair in entry.into_inner() {
+			match pair.as_rule() {
+				Rule::String => path = FormatString::build(pair, source).into(),
+				Rule::Identifier => namespace = Some(AST::build(pair, source)),
+				Rule::Use | Rule::As => continue,
+				_ => unreachable!(),
+			}
+		}
+		Import {
+			path,
+			namespace,
+			span,
 		}
 	}
 }
 
 impl AST for Block {
-	fn build(entry: Pair<'_, Rule>) -> Self {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
 		let mut executions: Vec<Execution> = vec![];
 		for pair in entry.into_inner() {
 			match pair.as_rule() {
@@ -339,12 +561,15 @@ impl AST for Block {
 					for pair in pair.into_inner() {
 						match pair.as_rule() {
 							Rule::ControlStmt => {
-								executions.push(Execution::ControlStatement(AST::build(pair)))
+								executions.push(Execution::ControlStatement(AST::build(pair, source)))
 							}
 							Rule::OpenStmt => {
-								executions.push(Execution::OpenStatement(AST::build(pair)))
+								executions.push(Execution::OpenStatement(AST::build(pair, source)))
+							}
+							Rule::Block => executions.push(Execution::Block(AST::build(pair, source))),
+							Rule::Function => {
+								executions.push(Execution::Function(AST::build(pair, source)))
 							}
-							Rule::Block => executions.push(Execution::Block(AST::build(pair))),
 							Rule::Semi => continue,
 							_ => unreachable!(),
 						}
@@ -359,28 +584,40 @@ impl AST for Block {
 }
 
 impl AST for Program {
-	fn build(entry: Pair<'_, Rule>) -> Self {
+	fn build(entry: Pair<'_, Rule>, source: &Arc<Source>) -> Self {
+		let mut imports: Vec<Import> = vec![];
 		let mut functions: Vec<Function> = vec![];
 		let mut executions: Vec<Execution> = vec![];
 		for pair in entry.into_inner() {
 			match pair.as_rule() {
-				Rule::ControlStmt => executions.push(Execution::ControlStatement(AST::build(pair))),
-				Rule::OpenStmt => executions.push(Execution::OpenStatement(AST::build(pair))),
-				Rule::Block => executions.push(Execution::Block(AST::build(pair))),
-				Rule::Function => functions.push(AST::build(pair)),
+				Rule::Import => imports.push(AST::build(pair, source)),
+				Rule::ControlStmt => executions.push(Execution::ControlStatement(AST::build(pair, source))),
+				Rule::OpenStmt => executions.push(Execution::OpenStatement(AST::build(pair, source))),
+				Rule::Block => executions.push(Execution::Block(AST::build(pair, source))),
+				Rule::Function => functions.push(AST::build(pair, source)),
 				Rule::Semi | Rule::EOI => continue,
 				_ => unreachable!(),
 			}
 		}
 		Program {
+			imports,
 			functions,
 			executions,
 		}
 	}
 }
 
-pub fn parse(input: &str) -> Result<Program, Error<Rule>> {
-	let mut pairs = Dog3Parser::parse(Rule::Program, input)?;
+// Errors are boxed, since they're much larger than a program.
+pub fn parse(input: &str) -> Result<Program, Box<Error<Rule>>> {
+	parse_source(Source::new(None, input))
+}
+
+pub fn parse_file(path: &str, input: &str) -> Result<Program, Box<Error<Rule>>> {
+	parse_source(Source::new(Some(path), input)).map_err(|err| Box::new(err.with_path(path)))
+}
+
+fn parse_source(source: Arc<Source>) -> Result<Program, Box<Error<Rule>>> {
+	let mut pairs = Dog3Parser::parse(Rule::Program, &source.text)?;
 	let root = pairs.next().unwrap();
-	Ok(AST::build(root))
+	Ok(AST::build(root, &source))
 }
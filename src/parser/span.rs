@@ -0,0 +1,89 @@
+use std::{fmt::Display, sync::Arc};
+
+#[derive(Debug)]
+pub struct Source {
+	pub name: Option<String>,
+	pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Span {
+	source: Arc<Source>,
+	start: usize,
+	end: usize,
+}
+
+impl Source {
+	pub fn new(name: Option<&str>, text: &str) -> Arc<Source> {
+		Arc::new(Source {
+			name: name.map(|name| name.to_owned()),
+			text: text.to_owned(),
+		})
+	}
+}
+
+impl Span {
+	pub fn new(source: &Arc<Source>, span: pest::Span<'_>) -> Span {
+		Span {
+			source: source.clone(),
+			start: span.start(),
+			end: span.end(),
+		}
+	}
+	// Narrows the span down to the `start..end` byte range relative to its
+	// own beginning.
+	pub fn sub(&self, start: usize, end: usize) -> Span {
+		Span {
+			source: self.source.clone(),
+			start: (self.start + start).min(self.end),
+			end: (self.start + end).min(self.end),
+		}
+	}
+	pub fn as_str(&self) -> &str {
+		&self.source.text[self.start..self.end]
+	}
+	pub fn name(&self) -> Option<&str> {
+		self.source.name.as_deref()
+	}
+	fn line_start(&self) -> usize {
+		self.source.text[..self.start]
+			.rfind('\n')
+			.map(|i| i + 1)
+			.unwrap_or(0)
+	}
+	pub fn line_col(&self) -> (usize, usize) {
+		let before = &self.source.text[..self.start];
+		let line = before.matches('\n').count() + 1;
+		let col = before[self.line_start()..].chars().count() + 1;
+		(line, col)
+	}
+	// Renders the location as a snippet of the offending line with the span
+	// underlined, in the same layout pest uses for syntax errors.
+	pub fn snippet(&self) -> String {
+		let (line, _) = self.line_col();
+		let text = &self.source.text[self.line_start()..];
+		let text = text.lines().next().unwrap_or("");
+		// Keep tabs so the carets line up with the text above them.
+		let indent: String = self.source.text[self.line_start()..self.start]
+			.chars()
+			.map(|c| if c == '\t' { '\t' } else { ' ' })
+			.collect();
+		let underline = self.as_str().lines().next().unwrap_or("").chars().count();
+		let number = line.to_string();
+		let pad = " ".repeat(number.len());
+		format!(
+			"{pad}--> {self}\n{pad} |\n{number} | {text}\n{pad} | {indent}{}",
+			"^".repeat(underline.max(1)),
+		)
+	}
+}
+
+impl Display for Span {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let (line, col) = self.line_col();
+		match self.name() {
+			Some(name) => write!(f, "{}:{}:{}", name, line, col),
+			None => write!(f, "{}:{}", line, col),
+		}
+	}
+}
@@ -0,0 +1,158 @@
+use std::{
+	io::{self, BufRead, Write},
+	process::ExitCode,
+};
+
+use dog3::{
+	parser::parse,
+	runtime::{output::Output, ExecutionError, Runtime},
+};
+
+use crate::exit_code;
+
+const PROMPT: &str = "dog3> ";
+const CONTINUE: &str = "  ... ";
+
+// Counts how many blocks, block comments and quotes are still open at the end
+// of `source`. Used to decide whether the REPL should keep reading lines
+// before parsing.
+fn open_depth(source: &str) -> usize {
+	let mut depth = 0usize;
+	let mut comments = 0usize;
+	let mut quote = None;
+	let mut chars = source.chars().peekable();
+	while let Some(c) = chars.next() {
+		if comments > 0 {
+			match (c, chars.peek()) {
+				('/', Some('*')) => {
+					chars.next();
+					comments += 1;
+				}
+				('*', Some('/')) => {
+					chars.next();
+					comments -= 1;
+				}
+				_ => (),
+			}
+			continue;
+		}
+		if let Some(q) = quote {
+			match c {
+				'\\' if q == '"' => {
+					chars.next();
+				}
+				c if c == q => quote = None,
+				_ => (),
+			}
+			continue;
+		}
+		match (c, chars.peek()) {
+			('\\', _) => {
+				chars.next();
+			}
+			('/', Some('/')) => {
+				while chars.next_if(|&c| c != '\n').is_some() {}
+			}
+			('/', Some('*')) => {
+				chars.next();
+				comments += 1;
+			}
+			('"', _) | ('\'', _) => quote = Some(c),
+			('{', _) => depth += 1,
+			('}', _) => depth = depth.saturating_sub(1),
+			_ => (),
+		}
+	}
+	depth + comments + quote.is_some() as usize
+}
+
+fn prompt(text: &str) -> io::Result<()> {
+	print!("{}", text);
+	io::stdout().flush()
+}
+
+pub async fn run(runtime: &mut Runtime) -> io::Result<ExitCode> {
+	let mut source = String::new();
+	prompt(PROMPT)?;
+	for line in io::stdin().lock().lines() {
+		source.push_str(&line?);
+		source.push('\n');
+		if open_depth(&source) > 0 {
+			prompt(CONTINUE)?;
+			continue;
+		}
+		if !source.trim().is_empty() {
+			if let Some(exit) = evaluate(runtime, &source).await {
+				print!("{}", exit.value());
+				return Ok(exit_code(exit.code()));
+			}
+		}
+		source.clear();
+		prompt(PROMPT)?;
+	}
+	println!();
+	Ok(ExitCode::SUCCESS)
+}
+
+// Runs a chunk of input, returning what's left to print if it called `exit`.
+async fn evaluate(runtime: &mut Runtime, source: &str) -> Option<Output> {
+	let program = match parse(source) {
+		Ok(program) => program,
+		Err(err) => {
+			eprintln!("error: Incorrect syntax.\n{}", err);
+			return None;
+		}
+	};
+	let executions = match runtime.load(program, None) {
+		Ok(executions) => executions,
+		Err(err) => {
+			eprintln!("{}", err);
+			return None;
+		}
+	};
+	match runtime.execute(&executions).await {
+		Ok(output) => {
+			let value = output.value();
+			print!("{}", value);
+			if !value.is_empty() && !value.ends_with('\n') {
+				println!();
+			}
+			println!("[status {}]", output.code());
+		}
+		Err(ExecutionError::Exit(output)) => return Some(output),
+		Err(err) => eprintln!("{}", err),
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::open_depth;
+
+	#[test]
+	fn counts_open_blocks() {
+		assert_eq!(open_depth("put a\n"), 0);
+		assert_eq!(open_depth("fn f {\n"), 1);
+		assert_eq!(open_depth("fn f { if a {\n"), 2);
+		assert_eq!(open_depth("fn f { put a }\n"), 0);
+		assert_eq!(open_depth("}}\n"), 0);
+		assert_eq!(open_depth("put \\{\n"), 0);
+	}
+
+	#[test]
+	fn ignores_braces_in_quotes() {
+		assert_eq!(open_depth("put \"{\"\n"), 0);
+		assert_eq!(open_depth("put '{'\n"), 0);
+		assert_eq!(open_depth("put \"a\n"), 1);
+		assert_eq!(open_depth("put \"a\\\"\n"), 1);
+		assert_eq!(open_depth("put 'a\\'\n"), 0);
+	}
+
+	#[test]
+	fn ignores_braces_in_comments() {
+		assert_eq!(open_depth("put a // {\n"), 0);
+		assert_eq!(open_depth("/* {\n"), 1);
+		assert_eq!(open_depth("/* /* */\n"), 1);
+		assert_eq!(open_depth("/* { */ {\n"), 1);
+	}
+}
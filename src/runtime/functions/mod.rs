@@ -2,6 +2,8 @@ mod builtin;
 
 use crate::parser::grammar::{Block, FormalParameter, Function};
 pub use builtin::*;
+
+use super::{lambdas::LambdaTable, maps::MapTable, regexes::RegexCache, scope::Captures};
 use std::{collections::HashMap, fmt::Display, sync::Arc};
 
 pub enum Runnable {
@@ -16,11 +18,20 @@ pub struct AnonymousFunction {
     pub max_args: usize,
     pub runnable: Arc<Runnable>,
     pub script: Option<String>,
+    pub captures: Arc<Captures>,
+    // The namespace of the module it comes from, if it was imported under
+    // one. The module's other functions can be passed to builtins by their
+    // plain names from inside it.
+    pub namespace: Option<String>,
 }
 
 #[derive(Clone)]
 pub struct FunctionLibrary {
     functions: HashMap<String, Vec<AnonymousFunction>>,
+    // These are shared between clones, so handles stay valid inside `eval`.
+    lambdas: LambdaTable,
+    maps: MapTable,
+    regexes: RegexCache,
 }
 
 #[derive(Debug)]
@@ -80,9 +91,23 @@ impl AnonymousFunction {
             args,
             runnable,
             script,
+            captures: Arc::new(Captures::default()),
+            namespace: None,
         }
     }
-    fn collides(&self, other: &AnonymousFunction) -> bool {
+    pub fn closure(
+        args: Vec<FormalParameter>,
+        block: Block,
+        script: String,
+        captures: Captures,
+        namespace: Option<String>,
+    ) -> AnonymousFunction {
+        let mut anon = AnonymousFunction::new(args, Arc::new(Runnable::Block(block)), Some(script));
+        anon.captures = Arc::new(captures);
+        anon.namespace = namespace;
+        anon
+    }
+    pub fn collides(&self, other: &AnonymousFunction) -> bool {
         self.min_args <= other.max_args && self.max_args >= other.min_args
     }
     fn is_builtin(&self) -> bool {
@@ -97,6 +122,9 @@ impl FunctionLibrary {
     pub fn new() -> FunctionLibrary {
         FunctionLibrary {
             functions: HashMap::new(),
+            lambdas: LambdaTable::default(),
+            maps: MapTable::default(),
+            regexes: RegexCache::default(),
         }
     }
     pub fn add_builtin(
@@ -130,7 +158,8 @@ impl FunctionLibrary {
         script: Option<String>,
     ) -> Result<String, RegisterError> {
         let current = self.functions.get_mut(name);
-        let anon = AnonymousFunction::new(args, runnable, script);
+        let mut anon = AnonymousFunction::new(args, runnable, script);
+        anon.namespace = name.rsplit_once("::").map(|(namespace, _)| namespace.to_owned());
 
         match current {
             Some(funcs) => {
@@ -166,6 +195,18 @@ impl FunctionLibrary {
         Ok(format!("Registered {} functions", count))
     }
 
+    pub fn lambdas(&self) -> &LambdaTable {
+        &self.lambdas
+    }
+
+    pub fn maps(&self) -> &MapTable {
+        &self.maps
+    }
+
+    pub fn regexes(&self) -> &RegexCache {
+        &self.regexes
+    }
+
     pub fn get_list(&self, name: &str) -> Option<&Vec<AnonymousFunction>> {
         self.functions.get(name)
     }
@@ -0,0 +1,184 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    ops::Deref,
+    sync::{Arc, Mutex, Weak},
+};
+
+use super::output::Output;
+
+type Token = Arc<dyn Any + Send + Sync>;
+
+// The lambdas and maps an output holds handles to. Whatever a handle refers
+// to lives for as long as some output, variable, map or lambda holds it, and
+// anything made from an output holds what it did, even if its text changed.
+// Outputs made from one another share the same set until one of them changes.
+#[derive(Clone, Default)]
+pub struct Held {
+    tokens: Option<Arc<HashMap<usize, Token>>>,
+}
+
+fn address<T: ?Sized>(token: &Arc<T>) -> usize {
+    Arc::as_ptr(token) as *const () as usize
+}
+
+impl Held {
+    pub fn of(entry: Token) -> Held {
+        Held {
+            tokens: Some(Arc::new(HashMap::from([(address(&entry), entry)]))),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_none()
+    }
+    pub fn merge(&mut self, other: &Held) {
+        let Some(theirs) = &other.tokens else {
+            return;
+        };
+        let ours = match &mut self.tokens {
+            Some(ours) => ours,
+            None => {
+                self.tokens = Some(theirs.clone());
+                return;
+            }
+        };
+        if Arc::ptr_eq(ours, theirs) || theirs.keys().all(|at| ours.contains_key(at)) {
+            return;
+        }
+        let ours = Arc::make_mut(ours);
+        for (at, token) in theirs.iter() {
+            ours.entry(*at).or_insert_with(|| token.clone());
+        }
+    }
+    // Stops holding `entry`, so that storing a value inside what it refers
+    // to doesn't keep it alive forever.
+    pub fn release<T>(&mut self, entry: &Arc<Entry<T>>) {
+        let Some(ours) = &mut self.tokens else {
+            return;
+        };
+        if ours.contains_key(&address(entry)) {
+            Arc::make_mut(ours).remove(&address(entry));
+            if ours.is_empty() {
+                self.tokens = None;
+            }
+        }
+    }
+}
+
+struct Entries<T> {
+    next: usize,
+    live: HashMap<usize, Weak<Entry<T>>>,
+}
+
+// What a handle refers to. It's dropped along with the last output holding
+// it, which also makes its handle stop working.
+pub struct Entry<T> {
+    id: usize,
+    entries: Weak<Mutex<Entries<T>>>,
+    payload: T,
+}
+
+impl<T> Deref for Entry<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.payload
+    }
+}
+
+impl<T> Drop for Entry<T> {
+    fn drop(&mut self) {
+        if let Some(entries) = self.entries.upgrade() {
+            entries.lock().unwrap().live.remove(&self.id);
+        }
+    }
+}
+
+// Things that are passed around by handle, such as `lambda#3`. Clones share
+// the same entries. Handles are never reused, so one that outlived what it
+// referred to can't end up reaching something else.
+pub struct HandleTable<T> {
+    prefix: &'static str,
+    entries: Arc<Mutex<Entries<T>>>,
+}
+
+impl<T> Clone for HandleTable<T> {
+    fn clone(&self) -> Self {
+        HandleTable {
+            prefix: self.prefix,
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> HandleTable<T> {
+    pub fn new(prefix: &'static str) -> HandleTable<T> {
+        HandleTable {
+            prefix,
+            entries: Arc::new(Mutex::new(Entries {
+                next: 0,
+                live: HashMap::new(),
+            })),
+        }
+    }
+    // Outputs a handle to `payload`, holding on to it.
+    pub fn create(&self, payload: T) -> Output {
+        let mut entries = self.entries.lock().unwrap();
+        let id = entries.next;
+        entries.next += 1;
+        let entry = Arc::new(Entry {
+            id,
+            entries: Arc::downgrade(&self.entries),
+            payload,
+        });
+        entries.live.insert(id, Arc::downgrade(&entry));
+        Output::new_truthy_with(format!("{}{}", self.prefix, id).into()).holding(entry)
+    }
+    pub fn get(&self, handle: &str) -> Option<Arc<Entry<T>>> {
+        let id: usize = handle.strip_prefix(self.prefix)?.parse().ok()?;
+        self.entries.lock().unwrap().live.get(&id)?.upgrade()
+    }
+    #[cfg(test)]
+    pub fn live(&self) -> usize {
+        self.entries.lock().unwrap().live.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_live_as_long_as_they_are_held() {
+        let table = HandleTable::new("thing#");
+        let handle = table.create(1);
+        assert_eq!(handle.value(), "thing#0");
+        let mut copy = Output::new_truthy_with("THING#0".into());
+        copy.append(&handle);
+        drop(handle);
+        assert_eq!(table.get("thing#0").map(|entry| **entry), Some(1));
+        drop(copy);
+        assert!(table.get("thing#0").is_none());
+        assert_eq!(table.live(), 0);
+        assert_eq!(table.create(2).value(), "thing#1");
+    }
+
+    #[test]
+    fn held_handles_are_dropped_along_with_their_holder() {
+        let inner = HandleTable::new("inner#");
+        let outer = HandleTable::new("outer#");
+        let held = outer.create(inner.create(()));
+        assert_eq!(inner.live(), 1);
+        drop(held);
+        assert_eq!((inner.live(), outer.live()), (0, 0));
+    }
+
+    #[test]
+    fn released_entries_are_not_held() {
+        let table = HandleTable::new("thing#");
+        let handle = table.create(());
+        let mut held = handle.held().clone();
+        held.release(&table.get(handle.value()).unwrap());
+        assert!(held.is_empty());
+    }
+}
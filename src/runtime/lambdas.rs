@@ -0,0 +1,24 @@
+use super::{functions::AnonymousFunction, handles::HandleTable, output::Output};
+
+// Lambdas live here and are passed around by handle.
+#[derive(Clone)]
+pub struct LambdaTable {
+    lambdas: HandleTable<AnonymousFunction>,
+}
+
+impl Default for LambdaTable {
+    fn default() -> Self {
+        LambdaTable {
+            lambdas: HandleTable::new("lambda#"),
+        }
+    }
+}
+
+impl LambdaTable {
+    pub fn create(&self, anon: AnonymousFunction) -> Output {
+        self.lambdas.create(anon)
+    }
+    pub fn get(&self, handle: &str) -> Option<AnonymousFunction> {
+        self.lambdas.get(handle).map(|anon| (*anon).clone())
+    }
+}
@@ -0,0 +1,42 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use super::{handles::HandleTable, output::Output};
+
+// Values hold on to the handles in their keys as well as their own.
+pub type Map = BTreeMap<String, Output>;
+
+// Maps live here and are passed around by handle, so that updating one doesn't
+// mean rebuilding a whole string.
+#[derive(Clone)]
+pub struct MapTable {
+    maps: HandleTable<Mutex<Map>>,
+}
+
+impl Default for MapTable {
+    fn default() -> Self {
+        MapTable {
+            maps: HandleTable::new("map#"),
+        }
+    }
+}
+
+impl MapTable {
+    pub fn create(&self) -> Output {
+        self.maps.create(Mutex::default())
+    }
+    // Runs `f` on the map behind `handle`, if there's one.
+    pub fn with<R>(&self, handle: &str, f: impl FnOnce(&mut Map) -> R) -> Option<R> {
+        let map = self.maps.get(handle)?;
+        let res = f(&mut map.lock().unwrap());
+        Some(res)
+    }
+    // Sets `key` to `value` in the map behind `handle`. The map doesn't hold
+    // on to itself, or it could never be dropped.
+    pub fn insert(&self, handle: &str, key: &Output, mut value: Output) -> Option<()> {
+        let map = self.maps.get(handle)?;
+        value.hold(key.held());
+        value.release(&map);
+        map.lock().unwrap().insert(key.value().to_owned(), value);
+        Some(())
+    }
+}
@@ -1,20 +1,33 @@
 pub mod functions;
+pub mod handles;
+pub mod lambdas;
+pub mod maps;
+pub mod modules;
 pub mod output;
+pub mod references;
+pub mod regexes;
 pub mod scope;
+pub mod trace;
 
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::HashMap, fmt::Display, io::Write, path::Path};
 
-use crate::parser::{format_string::*, grammar::*};
+use crate::parser::{format_string::*, grammar::*, span::Span};
 
 use async_recursion::async_recursion;
 use functions::*;
+use handles::Held;
+use modules::*;
 use output::*;
+use references::References;
 use scope::ScopeStack;
 use scope::*;
+use trace::*;
 
 pub struct Runtime {
     pub library: FunctionLibrary,
     pub globals: Scope,
+    pub calls: CallStack,
+    pub modules: ModuleLoader,
 }
 
 #[derive(Debug)]
@@ -28,22 +41,28 @@ pub enum Next {
     Append(Output),
     Return(Output),
     Clear(Output),
+    Break(Output, Span),
+    Continue(Output, Span),
     Abort(ExecutionError),
 }
 
 #[derive(Debug)]
 pub enum ExecutionError {
-    UndeclaredVariable(String),
-    UndefinedFunction(String),
-    UndefinedOverload(String, usize),
+    UndeclaredVariable(String, Span),
+    UndefinedFunction(String, Span),
+    UndefinedOverload(String, usize, Span),
+    OutsideLoop(&'static str, Span),
+    InvalidRegex(regex::Error, Span),
     InternalError,
     Custom(String),
+    Traced(Box<ExecutionError>, Vec<Frame>),
+    Exit(Output),
 }
 
 macro_rules! scoped {
     ($stack:expr, $block:block) => {{
         $stack.push();
-        let res = (async || $block)().await;
+        let res = async $block.await;
         $stack.pop();
         res
     }};
@@ -57,6 +76,53 @@ impl Next {
             self
         }
     }
+    // A `break` or `continue` that got this far has no loop left to stop.
+    fn outside_loop(self) -> Next {
+        match self {
+            Next::Break(_, span) => Next::Abort(ExecutionError::OutsideLoop("break", span)),
+            Next::Continue(_, span) => Next::Abort(ExecutionError::OutsideLoop("continue", span)),
+            other => other,
+        }
+    }
+}
+
+impl ExecutionError {
+    // Attaches the chain of calls that led to the error. Errors are traced
+    // where they happen, so an error that already has a trace keeps it.
+    pub fn traced(self, calls: &[Frame]) -> ExecutionError {
+        match self {
+            ExecutionError::Traced(..) | ExecutionError::Exit(_) => self,
+            _ => {
+                let calls: Vec<Frame> = calls.iter().filter(|frame| !frame.builtin).cloned().collect();
+                if calls.is_empty() {
+                    self
+                } else {
+                    ExecutionError::Traced(Box::new(self), calls)
+                }
+            }
+        }
+    }
+    // The error as it would be shown, minus the traceback.
+    pub fn message(&self) -> String {
+        match self {
+            ExecutionError::Traced(err, _) => err.message(),
+            other => other.to_string(),
+        }
+    }
+    // A short name for what went wrong, so `catch` can tell errors apart.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ExecutionError::UndeclaredVariable(..) => "undeclared_variable",
+            ExecutionError::UndefinedFunction(..) => "undefined_function",
+            ExecutionError::UndefinedOverload(..) => "undefined_overload",
+            ExecutionError::OutsideLoop(..) => "outside_loop",
+            ExecutionError::InvalidRegex(..) => "invalid_regex",
+            ExecutionError::InternalError => "internal",
+            ExecutionError::Custom(_) => "panic",
+            ExecutionError::Traced(err, _) => err.kind(),
+            ExecutionError::Exit(_) => "exit",
+        }
+    }
 }
 
 impl From<Result<Output, ExecutionError>> for Next {
@@ -71,21 +137,52 @@ impl From<Result<Output, ExecutionError>> for Next {
 impl Display for ExecutionError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ExecutionError::UndeclaredVariable(var) => {
-                write!(f, "error: Use of undeclared variable `{}`", var)
+            ExecutionError::UndeclaredVariable(var, span) => {
+                write!(
+                    f,
+                    "error: Use of undeclared variable `{}`\n{}",
+                    var,
+                    span.snippet()
+                )
             }
-            ExecutionError::UndefinedFunction(func) => {
-                write!(f, "error: Use of undefined function `{}`", func)
+            ExecutionError::UndefinedFunction(func, span) => {
+                write!(
+                    f,
+                    "error: Use of undefined function `{}`\n{}",
+                    func,
+                    span.snippet()
+                )
+            }
+            ExecutionError::UndefinedOverload(func, arg_c, span) => {
+                write!(
+                    f,
+                    "error: no overload for function `{}` takes `{}` arguments\n{}",
+                    func,
+                    arg_c,
+                    span.snippet()
+                )
             }
-            ExecutionError::UndefinedOverload(func, arg_c) => {
+            ExecutionError::OutsideLoop(keyword, span) => {
                 write!(
                     f,
-                    "error: no overload for function `{}` takes `{}` arguments",
-                    func, arg_c
+                    "error: `{}` used outside of a loop\n{}",
+                    keyword,
+                    span.snippet()
                 )
             }
+            ExecutionError::InvalidRegex(err, span) => {
+                write!(f, "error: Invalid regex\n{}\n{}", span.snippet(), err)
+            }
             ExecutionError::InternalError => write!(f, "error: Internal runtime error"),
             ExecutionError::Custom(err) => write!(f, "{err}"),
+            ExecutionError::Traced(err, calls) => {
+                write!(f, "{}\ntraceback (most recent call last):", err)?;
+                for frame in calls {
+                    write!(f, "\n  {}", frame)?;
+                }
+                Ok(())
+            }
+            ExecutionError::Exit(output) => write!(f, "Exited with status {}", output.code()),
         }
     }
 }
@@ -100,23 +197,19 @@ macro_rules! evaluate {
     };
 }
 
-macro_rules! proceed {
-    ($x:expr) => {
-        match $x {
-            Next::Proceed => Next::Proceed,
-            other => return other,
-        }
-    };
-}
-
 async fn execute_string(stack: &mut ScopeStack<'_>, name: &FormatString) -> Next {
     let mut output = Output::new_truthy();
     for piece in name.into_iter() {
         match piece {
             FormatStringPiece::Raw(value) => output.append_str(value),
-            FormatStringPiece::Variable(var) => match stack.get_var(var) {
+            FormatStringPiece::Variable(var, span) => match stack.get_var(var) {
                 Some(value) => output.append(value),
-                None => return Next::Abort(ExecutionError::UndeclaredVariable(var.into())),
+                None => {
+                    return Next::Abort(ExecutionError::UndeclaredVariable(
+                        var.into(),
+                        span.clone(),
+                    ))
+                }
             },
         }
     }
@@ -135,6 +228,16 @@ async fn execute_value<'env, 'stack>(
         Value::ControlStatement(control) => {
             execute_control_statement(functions, stack, control).await
         }
+        Value::Lambda(lambda) => {
+            let closure = AnonymousFunction::closure(
+                lambda.args.clone(),
+                lambda.block.clone(),
+                lambda.script.clone(),
+                stack.capture(&References::of(&lambda.block)),
+                stack.namespace().map(str::to_owned),
+            );
+            Next::Append(functions.lambdas().create(closure))
+        }
     }
 }
 
@@ -158,6 +261,10 @@ async fn execute_open_statement<'env, 'stack>(
         OpenStatement::SetStmt(stmt) => execute_set_statement(functions, stack, stmt).await,
         OpenStatement::ReturnStmt(stmt) => execute_return_statement(functions, stack, stmt).await,
         OpenStatement::ClearStmt(stmt) => execute_clear_statement(functions, stack, stmt).await,
+        OpenStatement::BreakStmt(stmt) => execute_break_statement(functions, stack, stmt).await,
+        OpenStatement::ContinueStmt(stmt) => {
+            Next::Continue(Output::new_truthy(), stmt.span.clone())
+        }
         OpenStatement::CommandStmt(stmt) => execute_command_statement(functions, stack, stmt).await,
     }
 }
@@ -169,6 +276,7 @@ async fn execute_set_statement<'env, 'stack>(
 ) -> Next {
     match execute_value(functions, stack, &stmt.value).await {
         Next::Append(output) => {
+            stack.set_status(output.code());
             stack.set_var(stmt.variable.as_str(), output);
             Next::Proceed
         }
@@ -181,21 +289,30 @@ async fn execute_command_statement<'env, 'stack>(
     stack: &'stack mut ScopeStack<'env>,
     stmt: &CommandStatement,
 ) -> Next {
-    let func_list = functions.get_list(&stmt.name);
+    // Functions defined in an enclosing block shadow global ones.
+    let func_list = stack
+        .get_functions(&stmt.name)
+        .or_else(|| functions.get_list(&stmt.name));
     let func_list = match func_list {
         Some(value) => value,
-        None => return Next::Abort(ExecutionError::UndefinedFunction(stmt.name.to_owned())),
+        None => {
+            return Next::Abort(ExecutionError::UndefinedFunction(
+                stmt.name.to_owned(),
+                stmt.span.clone(),
+            ))
+        }
     };
     let count = stmt.parameters.len();
     let func = func_list
         .iter()
         .find(|x| x.max_args >= count && x.min_args <= count);
     let func = match func {
-        Some(func) => func,
+        Some(func) => func.clone(),
         None => {
             return Next::Abort(ExecutionError::UndefinedOverload(
                 stmt.name.to_owned(),
                 count,
+                stmt.span.clone(),
             ))
         }
     };
@@ -205,34 +322,107 @@ async fn execute_command_statement<'env, 'stack>(
         let output = evaluate!(execute_value(functions, stack, &arg.value).await);
         arg_values.push(output);
     }
-    match func.runnable.as_ref() {
+    let frame = Frame::new(&stmt.name, count, &stmt.span);
+    invoke(functions, stack, &func, frame, arg_values).await.into()
+}
+
+// Calls the function `name`, or the lambda it's a handle to, on behalf of the
+// builtin currently running. Errors point at where the builtin was called.
+pub async fn call_function<'env, 'stack>(
+    functions: &FunctionLibrary,
+    stack: &'stack mut ScopeStack<'env>,
+    name: &str,
+    args: Vec<Output>,
+) -> Result<Output, ExecutionError> {
+    let call_site = match stack.calls().last() {
+        Some(frame) => frame.call_site.clone(),
+        None => return Err(ExecutionError::InternalError),
+    };
+    // Code from a module imported under a namespace can pass the module's
+    // functions by their plain names.
+    let qualified = stack
+        .namespace()
+        .map(|namespace| format!("{}::{}", namespace, name));
+    let overloads = match functions.lambdas().get(name) {
+        Some(func) => vec![func],
+        None => match stack
+            .get_functions(name)
+            .or_else(|| functions.get_list(qualified.as_deref()?))
+            .or_else(|| functions.get_list(name))
+        {
+            Some(overloads) => overloads.clone(),
+            None => return Err(ExecutionError::UndefinedFunction(name.into(), call_site)),
+        },
+    };
+    let count = args.len();
+    let func = overloads
+        .into_iter()
+        .find(|x| x.max_args >= count && x.min_args <= count);
+    let func = match func {
+        Some(func) => func,
+        None => {
+            return Err(ExecutionError::UndefinedOverload(
+                name.into(),
+                count,
+                call_site,
+            ))
+        }
+    };
+    let frame = Frame::new(name, count, &call_site);
+    invoke(functions, stack, &func, frame, args).await
+}
+
+// Runs `func` with arguments that have already been evaluated, keeping track
+// of it in the call stack as `frame`. This is how builtins call back into
+// user functions.
+pub async fn invoke<'env, 'stack>(
+    functions: &FunctionLibrary,
+    stack: &'stack mut ScopeStack<'env>,
+    func: &AnonymousFunction,
+    frame: Frame,
+    mut arg_values: Vec<Output>,
+) -> Result<Output, ExecutionError> {
+    let builtin = matches!(func.runnable.as_ref(), Runnable::BuiltIn(_));
+    stack.push_call(Frame { builtin, ..frame });
+    let res = match func.runnable.as_ref() {
         Runnable::Block(block) => {
-            let mut func_stack = ScopeStack::call_frame(stack);
+            let mut func_stack = ScopeStack::call_frame(stack, func);
+            if let Some(name) = &func.captures.name {
+                func_stack.declare_function(name, func.clone());
+            }
             for arg in func.args.iter() {
                 if arg.vector {
-                    let joined_values = arg_values
-                        .iter()
-                        .map(|output| output.value())
-                        .collect::<Vec<&str>>()
-                        .join(" ");
-                    let last_code = arg_values.last().map(|o| o.code()).unwrap_or(0);
-                    func_stack.declare_var(&arg.name, Output::new(joined_values.into(), last_code));
+                    func_stack.declare_var(&arg.name, join_outputs(arg_values.iter()));
                     break;
                 } else {
                     func_stack.declare_var(&arg.name, arg_values.remove(0))
                 }
             }
-            let res = execute_block(functions, &mut func_stack, &block).await;
-            let res = match res {
-                Next::Return(out) => Next::Append(out),
-                other => other,
-            };
-            return res;
+            match execute_block(functions, &mut func_stack, block).await.outside_loop() {
+                Next::Append(out) | Next::Return(out) => Ok(out),
+                Next::Abort(err) => Err(err),
+                _ => Err(ExecutionError::InternalError),
+            }
         }
+        // What a builtin outputs is made from its arguments, so it holds on
+        // to whatever they did, unless it's empty.
         Runnable::BuiltIn(builtin) => {
-            return builtin.call(functions, stack, arg_values).await.into()
+            let mut held = Held::default();
+            for arg in arg_values.iter() {
+                held.merge(arg.held());
+            }
+            let res = builtin.call(functions, stack, arg_values).await;
+            res.map(|mut output| {
+                if !output.value().is_empty() {
+                    output.hold(&held);
+                }
+                output
+            })
         }
-    }
+    };
+    let res = res.map_err(|err| err.traced(stack.calls()));
+    stack.pop_call();
+    res
 }
 
 async fn execute_clear_statement<'env, 'stack>(
@@ -265,6 +455,18 @@ async fn execute_return_statement<'env, 'stack>(
     })
 }
 
+async fn execute_break_statement<'env, 'stack>(
+    functions: &FunctionLibrary,
+    stack: &'stack mut ScopeStack<'env>,
+    stmt: &BreakStatement,
+) -> Next {
+    let output = match &stmt.value {
+        Some(value) => evaluate!(execute_value(functions, stack, value).await),
+        None => Output::new_truthy(),
+    };
+    Next::Break(output, stmt.span.clone())
+}
+
 async fn execute_for_statement<'env, 'stack>(
     functions: &FunctionLibrary,
     stack: &'stack mut ScopeStack<'env>,
@@ -277,15 +479,56 @@ async fn execute_for_statement<'env, 'stack>(
             None => None,
             Some(split) => Some(evaluate!(execute_value(functions, stack, split).await)),
         };
-        for value in list.split_iter(split.as_ref()) {
+        // Like `nth` and `list_len`, refuse a malformed list as a whole,
+        // instead of running for the items before the broken one.
+        if stmt.encoded && decode_list(list.value()).is_none() {
+            return Next::Append(Output::new_falsy());
+        }
+        let items = if stmt.encoded {
+            list.list_iter()
+        } else {
+            list.split_iter(split.as_ref())
+        };
+        for value in items {
             tokio::task::yield_now().await;
-            stack.set_var(&stmt.variable, Output::new(value.to_owned().into(), 0));
-            proceed!(scoped!(stack, {
-                output.append(&evaluate!(
-                    execute_value(functions, stack, &stmt.output).await
-                ));
-                Next::Proceed
-            }));
+            stack.set_var(&stmt.variable, list.part(value));
+            match scoped!(stack, { execute_value(functions, stack, &stmt.output).await }) {
+                Next::Append(out) | Next::Continue(out, _) => output.append(&out),
+                Next::Break(out, _) => {
+                    output.append(&out);
+                    break;
+                }
+                other => return other,
+            }
+        }
+        Next::Append(output)
+    })
+}
+
+async fn execute_for_map_statement<'env, 'stack>(
+    functions: &FunctionLibrary,
+    stack: &'stack mut ScopeStack<'env>,
+    stmt: &ForMapStatement,
+) -> Next {
+    scoped!(stack, {
+        let mut output = Output::new_truthy();
+        let map = evaluate!(execute_value(functions, stack, &stmt.map).await);
+        // Iterate over a copy, so the loop is free to change the map.
+        let Some(entries) = functions.maps().with(map.value(), |map| map.clone()) else {
+            return Next::Append(Output::new_falsy());
+        };
+        for (key, value) in entries {
+            tokio::task::yield_now().await;
+            stack.set_var(&stmt.key, value.part(&key));
+            stack.set_var(&stmt.value, value);
+            match scoped!(stack, { execute_value(functions, stack, &stmt.output).await }) {
+                Next::Append(out) | Next::Continue(out, _) => output.append(&out),
+                Next::Break(out, _) => {
+                    output.append(&out);
+                    break;
+                }
+                other => return other,
+            }
         }
         Next::Append(output)
     })
@@ -326,17 +569,67 @@ async fn execute_while_statement<'env, 'stack>(
     let mut condition = evaluate!(execute_value(functions, stack, &stmt.condition).await);
     while condition.is_truthy() {
         tokio::task::yield_now().await;
-        proceed!(scoped!(stack, {
-            output.append(&evaluate!(
-                execute_value(functions, stack, &stmt.output).await
-            ));
-            Next::Proceed
-        }));
+        match scoped!(stack, { execute_value(functions, stack, &stmt.output).await }) {
+            Next::Append(out) | Next::Continue(out, _) => output.append(&out),
+            Next::Break(out, _) => {
+                output.append(&out);
+                break;
+            }
+            other => return other,
+        }
         condition = evaluate!(execute_value(functions, stack, &stmt.condition).await);
     }
     Next::Append(output)
 }
 
+async fn execute_match_statement<'env, 'stack>(
+    functions: &FunctionLibrary,
+    stack: &'stack mut ScopeStack<'env>,
+    stmt: &MatchStatement,
+) -> Next {
+    let value = evaluate!(execute_value(functions, stack, &stmt.value).await);
+    for arm in stmt.arms.iter() {
+        for pattern in arm.patterns.iter() {
+            let matched = match pattern {
+                Pattern::Wildcard => true,
+                Pattern::Text(text) => evaluate!(execute_string(stack, text).await).value() == value.value(),
+                Pattern::Regex(regex, span) => {
+                    let regex = evaluate!(execute_string(stack, regex).await);
+                    match functions.regexes().get(regex.value()) {
+                        Ok(regex) => regex.is_match(value.value()),
+                        Err(err) => {
+                            return Next::Abort(ExecutionError::InvalidRegex(err, span.clone()))
+                        }
+                    }
+                }
+            };
+            if matched {
+                return execute_value(functions, stack, &arm.output).await;
+            }
+        }
+    }
+    Next::Append(Output::new_falsy())
+}
+
+async fn execute_try_statement<'env, 'stack>(
+    functions: &FunctionLibrary,
+    stack: &'stack mut ScopeStack<'env>,
+    stmt: &TryStatement,
+) -> Next {
+    let err = match execute_value(functions, stack, &stmt.output).await {
+        Next::Abort(ExecutionError::Exit(output)) => return Next::Abort(ExecutionError::Exit(output)),
+        Next::Abort(err) => err,
+        other => return other,
+    };
+    scoped!(stack, {
+        stack.declare_var(&stmt.error, Output::new_truthy_with(err.message().into()));
+        if let Some(kind) = &stmt.kind {
+            stack.declare_var(kind, Output::new_truthy_with(err.kind().into()));
+        }
+        execute_value(functions, stack, &stmt.handler).await
+    })
+}
+
 async fn execute_control_statement<'env, 'stack>(
     functions: &FunctionLibrary,
     stack: &'stack mut ScopeStack<'env>,
@@ -344,6 +637,9 @@ async fn execute_control_statement<'env, 'stack>(
 ) -> Next {
     match control {
         ControlStatement::ForStatement(stmt) => execute_for_statement(functions, stack, stmt).await,
+        ControlStatement::ForMapStatement(stmt) => {
+            execute_for_map_statement(functions, stack, stmt).await
+        }
         ControlStatement::IfStatement(stmt) => execute_if_statement(functions, stack, stmt).await,
         ControlStatement::IfElseStatement(stmt) => {
             execute_if_else_statement(functions, stack, stmt).await
@@ -351,6 +647,54 @@ async fn execute_control_statement<'env, 'stack>(
         ControlStatement::WhileStatement(stmt) => {
             execute_while_statement(functions, stack, stmt).await
         }
+        ControlStatement::MatchStatement(stmt) => {
+            execute_match_statement(functions, stack, stmt).await
+        }
+        ControlStatement::TryStatement(stmt) => execute_try_statement(functions, stack, stmt).await,
+    }
+}
+
+// Keeps `$?` up to date with the status of the statement that just ran.
+fn track_status(stack: &mut ScopeStack<'_>, next: &Next) {
+    if let Next::Append(output) = next {
+        stack.set_status(output.code());
+    }
+}
+
+async fn execute_execution<'env, 'stack>(
+    functions: &FunctionLibrary,
+    stack: &'stack mut ScopeStack<'env>,
+    exec: &Execution,
+) -> Next {
+    match exec {
+        Execution::Block(block) => {
+            let next = execute_block(functions, stack, block).await;
+            track_status(stack, &next);
+            next.supress()
+        }
+        Execution::ControlStatement(control) => {
+            let next = execute_control_statement(functions, stack, control).await;
+            track_status(stack, &next);
+            next.supress()
+        }
+        Execution::OpenStatement(open) => {
+            let next = execute_open_statement(functions, stack, open).await;
+            track_status(stack, &next);
+            next
+        }
+        Execution::Function(func) => {
+            let mut captures = stack.capture(&References::of(&func.block));
+            captures.name = Some(func.name.clone());
+            let closure = AnonymousFunction::closure(
+                func.args.clone(),
+                func.block.clone(),
+                func.script.clone(),
+                captures,
+                stack.namespace().map(str::to_owned),
+            );
+            stack.declare_function(&func.name, closure);
+            Next::Proceed
+        }
     }
 }
 
@@ -360,49 +704,123 @@ async fn execute_statements<'env, 'stack>(
     execs: &[Execution],
 ) -> Next {
     let mut output = Output::new_truthy();
+    match execute_statements_into(functions, stack, execs, &mut output).await {
+        Next::Proceed => Next::Append(output),
+        // Loops keep what was output up to a `break` or `continue`.
+        Next::Break(out, span) => {
+            output.append(&out);
+            Next::Break(output, span)
+        }
+        Next::Continue(out, span) => {
+            output.append(&out);
+            Next::Continue(output, span)
+        }
+        other => other,
+    }
+}
+
+// Same as `execute_statements`, but appends to `output` as it goes, so
+// whatever was output before an early exit is still there.
+async fn execute_statements_into<'env, 'stack>(
+    functions: &FunctionLibrary,
+    stack: &'stack mut ScopeStack<'env>,
+    execs: &[Execution],
+    output: &mut Output,
+) -> Next {
     for exec in execs.iter() {
-        let next = match exec {
-            Execution::Block(block) => execute_block(functions, stack, block).await.supress(),
-            Execution::ControlStatement(control) => {
-                execute_control_statement(functions, stack, control)
-                    .await
-                    .supress()
-            }
-            Execution::OpenStatement(open) => execute_open_statement(functions, stack, open).await,
-        };
-        match next {
+        match execute_execution(functions, stack, exec).await {
             Next::Proceed => continue,
             Next::Append(out) => output.append(&out),
-            Next::Clear(out) => output = out,
+            Next::Clear(out) => *output = out,
             other => return other,
         }
     }
-    Next::Append(output)
+    Next::Proceed
 }
 
 impl Runtime {
     pub fn new() -> Runtime {
         Runtime {
             library: FunctionLibrary::new(),
-            globals: HashMap::new(),
+            globals: HashMap::from([(STATUS_VAR.to_owned(), Output::new_truthy_with("0".into()))]),
+            calls: vec![],
+            modules: ModuleLoader::new(),
         }
     }
+    // Registers the functions of `program`, along with everything it
+    // imports, and returns what's left to execute.
+    pub fn load(
+        &mut self,
+        program: Program,
+        path: Option<&Path>,
+    ) -> Result<Vec<Execution>, ModuleError> {
+        self.modules.load(&mut self.library, program, path)
+    }
     pub async fn execute(&mut self, execs: &[Execution]) -> Result<Output, ExecutionError> {
-        let mut glob = ScopeStack::new(&mut self.globals);
-        let res = execute_statements(&self.library, &mut glob, &execs).await;
+        let mut glob = ScopeStack::new(&mut self.globals, &mut self.calls);
+        let mut output = Output::new_truthy();
+        let res = execute_statements_into(&self.library, &mut glob, execs, &mut output)
+            .await
+            .outside_loop();
         match res {
-            Next::Append(output) => Ok(output),
+            Next::Proceed => Ok(output),
             Next::Return(output) => Ok(output),
+            Next::Abort(ExecutionError::Exit(exit)) => {
+                output.append(&exit);
+                Err(ExecutionError::Exit(output))
+            }
             Next::Abort(err) => Err(err),
             _ => Err(ExecutionError::InternalError),
         }
     }
+    // Like `execute`, but writes the output of each top-level statement to
+    // `sink` as soon as it's done, instead of holding on to all of it. Since
+    // that output is already gone, a top-level `clear` can only clear what
+    // the current statement outputs. Returns the final status code.
+    pub async fn stream(
+        &mut self,
+        execs: &[Execution],
+        sink: &mut dyn Write,
+    ) -> Result<i64, ExecutionError> {
+        let mut glob = ScopeStack::new(&mut self.globals, &mut self.calls);
+        let mut code = 0;
+        for exec in execs.iter() {
+            let next = execute_execution(&self.library, &mut glob, exec).await;
+            let (output, last) = match next.outside_loop() {
+                Next::Proceed => continue,
+                Next::Append(output) | Next::Clear(output) => (output, false),
+                Next::Return(output) | Next::Abort(ExecutionError::Exit(output)) => (output, true),
+                Next::Abort(err) => return Err(err),
+                Next::Break(..) | Next::Continue(..) => unreachable!(),
+            };
+            code = output.code();
+            let written = sink
+                .write_all(output.value().as_bytes())
+                .and_then(|_| sink.flush());
+            if let Err(err) = written {
+                // Whoever was reading has stopped, so there's no point going on.
+                if err.kind() == std::io::ErrorKind::BrokenPipe {
+                    break;
+                }
+                return Err(ExecutionError::Custom(format!(
+                    "error: Failed to write output.\n{}",
+                    err
+                )));
+            }
+            if last {
+                break;
+            }
+        }
+        Ok(code)
+    }
     pub async fn execute_scoped<'env, 'stack>(
         &self,
         stack: &'stack mut ScopeStack<'env>,
         execs: &[Execution],
     ) -> Result<Output, ExecutionError> {
-        let res = execute_statements(&self.library, stack, &execs).await;
+        let res = execute_statements(&self.library, stack, &execs)
+            .await
+            .outside_loop();
         match res {
             Next::Append(output) => Ok(output),
             Next::Return(output) => Ok(output),
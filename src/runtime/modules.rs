@@ -0,0 +1,249 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::parser::{grammar::*, parse_file, span::Span, Rule};
+
+use super::functions::{FunctionLibrary, RegisterError};
+
+#[derive(Default)]
+pub struct ModuleLoader {
+    loaded: HashMap<PathBuf, Vec<Function>>,
+    imported: HashSet<(PathBuf, Option<String>)>,
+    loading: Vec<PathBuf>,
+}
+
+#[derive(Debug)]
+pub enum ModuleError {
+    IO(String, std::io::Error, Span),
+    Syntax(Box<pest::error::Error<Rule>>),
+    Register(RegisterError),
+    Cycle(Vec<PathBuf>, Span),
+}
+
+impl Display for ModuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModuleError::IO(path, err, span) => {
+                write!(
+                    f,
+                    "error: Failed to import `{}`: {}\n{}",
+                    path,
+                    err,
+                    span.snippet()
+                )
+            }
+            ModuleError::Syntax(err) => write!(f, "error: Incorrect syntax.\n{}", err),
+            ModuleError::Register(err) => write!(f, "error: {}", err),
+            ModuleError::Cycle(chain, span) => {
+                let chain = itertools::join(chain.iter().map(|path| path.display()), " -> ");
+                write!(f, "error: Import cycle detected: {}\n{}", chain, span.snippet())
+            }
+        }
+    }
+}
+
+impl From<Box<pest::error::Error<Rule>>> for ModuleError {
+    fn from(value: Box<pest::error::Error<Rule>>) -> Self {
+        Self::Syntax(value)
+    }
+}
+
+impl From<RegisterError> for ModuleError {
+    fn from(value: RegisterError) -> Self {
+        Self::Register(value)
+    }
+}
+
+fn rename_block(block: &mut Block, rename: &dyn Fn(&str) -> Option<String>) {
+    for exec in block.executions.iter_mut() {
+        match exec {
+            Execution::Block(block) => rename_block(block, rename),
+            Execution::ControlStatement(control) => rename_control(control, rename),
+            Execution::OpenStatement(open) => rename_open(open, rename),
+            Execution::Function(func) => rename_block(&mut func.block, rename),
+        }
+    }
+}
+
+fn rename_value(value: &mut Value, rename: &dyn Fn(&str) -> Option<String>) {
+    match value {
+        Value::String(_) => (),
+        Value::Block(block) => rename_block(block, rename),
+        Value::ControlStatement(control) => rename_control(control, rename),
+        Value::Lambda(lambda) => rename_block(&mut lambda.block, rename),
+    }
+}
+
+fn rename_control(control: &mut ControlStatement, rename: &dyn Fn(&str) -> Option<String>) {
+    match control {
+        ControlStatement::ForStatement(stmt) => {
+            rename_value(&mut stmt.list, rename);
+            if let Some(split) = &mut stmt.split {
+                rename_value(split, rename);
+            }
+            rename_value(&mut stmt.output, rename);
+        }
+        ControlStatement::ForMapStatement(stmt) => {
+            rename_value(&mut stmt.map, rename);
+            rename_value(&mut stmt.output, rename);
+        }
+        ControlStatement::IfStatement(stmt) => {
+            rename_value(&mut stmt.condition, rename);
+            rename_value(&mut stmt.output, rename);
+        }
+        ControlStatement::IfElseStatement(stmt) => {
+            rename_value(&mut stmt.condition, rename);
+            rename_value(&mut stmt.output_true, rename);
+            rename_value(&mut stmt.output_false, rename);
+        }
+        ControlStatement::WhileStatement(stmt) => {
+            rename_value(&mut stmt.condition, rename);
+            rename_value(&mut stmt.output, rename);
+        }
+        ControlStatement::TryStatement(stmt) => {
+            rename_value(&mut stmt.output, rename);
+            rename_value(&mut stmt.handler, rename);
+        }
+        ControlStatement::MatchStatement(stmt) => {
+            rename_value(&mut stmt.value, rename);
+            for arm in stmt.arms.iter_mut() {
+                rename_value(&mut arm.output, rename);
+            }
+        }
+    }
+}
+
+fn rename_open(open: &mut OpenStatement, rename: &dyn Fn(&str) -> Option<String>) {
+    match open {
+        OpenStatement::SetStmt(stmt) => rename_value(&mut stmt.value, rename),
+        OpenStatement::ReturnStmt(ReturnStatement { value })
+        | OpenStatement::ClearStmt(ClearStatement { value })
+        | OpenStatement::BreakStmt(BreakStatement { value, .. }) => {
+            if let Some(value) = value {
+                rename_value(value, rename);
+            }
+        }
+        OpenStatement::ContinueStmt(_) => (),
+        OpenStatement::CommandStmt(stmt) => {
+            if let Some(name) = rename(&stmt.name) {
+                stmt.name = name;
+            }
+            for param in stmt.parameters.iter_mut() {
+                rename_value(&mut param.value, rename);
+            }
+        }
+    }
+}
+
+// Moves every function under `namespace::`, including the calls they make to
+// each other, so that a module keeps working once it's been prefixed.
+fn qualify(mut functions: Vec<Function>, namespace: &str) -> Vec<Function> {
+    let locals: HashSet<String> = functions.iter().map(|func| func.name.clone()).collect();
+    let rename = |name: &str| {
+        locals
+            .contains(name)
+            .then(|| format!("{}::{}", namespace, name))
+    };
+    for func in functions.iter_mut() {
+        func.name = format!("{}::{}", namespace, func.name);
+        rename_block(&mut func.block, &rename);
+    }
+    functions
+}
+
+impl ModuleLoader {
+    pub fn new() -> ModuleLoader {
+        ModuleLoader::default()
+    }
+
+    // Registers the functions of `program` and everything it imports into
+    // `library`, returning the top-level executions of `program` itself.
+    // Imports are resolved relative to `path`, or to the working directory
+    // when there's none.
+    pub fn load(
+        &mut self,
+        library: &mut FunctionLibrary,
+        program: Program,
+        path: Option<&Path>,
+    ) -> Result<Vec<Execution>, ModuleError> {
+        let canonical = path.and_then(|path| fs::canonicalize(path).ok());
+        let base = match &canonical {
+            Some(path) => path.parent().unwrap_or(Path::new(".")).to_owned(),
+            None => PathBuf::from("."),
+        };
+        if let Some(path) = &canonical {
+            self.loading.push(path.clone());
+        }
+        let res = self.import_all(library, &program.imports, &base);
+        if let Some(path) = canonical {
+            self.loading.pop();
+            self.loaded.insert(path, program.functions.clone());
+        }
+        res?;
+        library.add_scripts(program.functions)?;
+        Ok(program.executions)
+    }
+
+    fn import_all(
+        &mut self,
+        library: &mut FunctionLibrary,
+        imports: &[Import],
+        base: &Path,
+    ) -> Result<(), ModuleError> {
+        for import in imports {
+            self.import(library, import, base)?;
+        }
+        Ok(())
+    }
+
+    fn import(
+        &mut self,
+        library: &mut FunctionLibrary,
+        import: &Import,
+        base: &Path,
+    ) -> Result<(), ModuleError> {
+        let path = base.join(&import.path);
+        let path = match fs::canonicalize(&path) {
+            Ok(path) => path,
+            Err(err) => return Err(ModuleError::IO(import.path.clone(), err, import.span.clone())),
+        };
+        if let Some(start) = self.loading.iter().position(|loading| *loading == path) {
+            let mut chain = self.loading[start..].to_vec();
+            chain.push(path);
+            return Err(ModuleError::Cycle(chain, import.span.clone()));
+        }
+        let key = (path.clone(), import.namespace.clone());
+        if self.imported.contains(&key) {
+            return Ok(());
+        }
+        let functions = match self.loaded.get(&path) {
+            Some(functions) => functions.clone(),
+            None => {
+                let content = match fs::read_to_string(&path) {
+                    Ok(content) => content,
+                    Err(err) => {
+                        return Err(ModuleError::IO(import.path.clone(), err, import.span.clone()))
+                    }
+                };
+                let program = parse_file(&path.to_string_lossy(), &content)?;
+                self.loading.push(path.clone());
+                let res = self.import_all(library, &program.imports, path.parent().unwrap());
+                self.loading.pop();
+                res?;
+                self.loaded.insert(path, program.functions.clone());
+                program.functions
+            }
+        };
+        let functions = match &import.namespace {
+            Some(namespace) => qualify(functions, namespace),
+            None => functions,
+        };
+        library.add_scripts(functions)?;
+        self.imported.insert(key);
+        Ok(())
+    }
+}
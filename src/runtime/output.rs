@@ -1,17 +1,28 @@
-use std::{borrow::Cow, fmt::{Display, Debug}};
+use std::{any::Any, borrow::Cow, fmt::{Display, Debug}, sync::Arc};
 
 use serde::{Deserialize, Serialize};
 
+use super::handles::{Entry, Held};
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Output {
 	value: Cow<'static, str>,
-	code: i64
+	code: i64,
+	#[serde(skip)]
+	held: Held,
 }
 
 pub enum OutputSplitIterator<'a> {
 	SplitChars(CharIterator<'a>),
 	Split(std::str::Split<'a, &'a str>),
 	SplitWhitespace(std::str::SplitWhitespace<'a>),
+	List(ListIterator<'a>),
+}
+
+// Walks a list encoded by `encode_list`. Stops early if the encoding is
+// broken; use `decode_list` to find out whether it was.
+pub struct ListIterator<'a> {
+	input: &'a str,
 }
 
 pub struct CharIterator<'a> {
@@ -19,12 +30,32 @@ pub struct CharIterator<'a> {
 	index: usize,
 }
 
+impl<'a> ListIterator<'a> {
+	fn new(input: &'a str) -> ListIterator<'a> {
+		ListIterator { input }
+	}
+}
+
 impl<'a> CharIterator<'a> {
 	fn new(input: &'a str) -> CharIterator<'a> {
 		CharIterator { input, index: 0 }
 	}
 }
 
+// Lists are encoded as each item prefixed by its length in bytes and a colon,
+// separated by spaces, such as `3:a b 0: 1:c`. This way any string can be
+// an item, no matter what characters it contains.
+pub fn encode_list<'a, I: Iterator<Item = &'a str>>(items: I) -> String {
+	let items = items.map(|item| format!("{}:{}", item.len(), item));
+	itertools::join(items, " ")
+}
+
+pub fn decode_list(input: &str) -> Option<Vec<&str>> {
+	let mut iter = ListIterator::new(input);
+	let items: Vec<&str> = iter.by_ref().collect();
+	iter.input.is_empty().then_some(items)
+}
+
 pub fn join_outputs<'a, 'b, I: Iterator<Item = &'b Output>>(outputs: I) -> Output {
 	let mut result = Output::new_truthy();
 	let mut first = true;
@@ -41,7 +72,7 @@ pub fn join_outputs<'a, 'b, I: Iterator<Item = &'b Output>>(outputs: I) -> Outpu
 
 impl Output {
 	pub fn new(value: Cow<'static, str>, code: i64) -> Output {
-		Output { value, code }
+		Output { value, code, held: Held::default() }
 	}
 	pub fn new_truthy() -> Output {
 		Self::new("".into(), 0)
@@ -55,9 +86,33 @@ impl Output {
 	pub fn new_falsy_with(value: Cow<'static, str>) -> Output {
 		Self::new(value, 1)
 	}
+	// A truthy output with `value`, taken from this one's, that holds on to
+	// the same handles.
+	pub fn part(&self, value: &str) -> Output {
+		let mut part = Output::new_truthy_with(value.to_owned().into());
+		part.hold(&self.held);
+		part
+	}
+	pub fn holding(mut self, entry: Arc<dyn Any + Send + Sync>) -> Output {
+		self.held.merge(&Held::of(entry));
+		self
+	}
+	pub fn held(&self) -> &Held {
+		&self.held
+	}
+	pub fn hold(&mut self, held: &Held) {
+		self.held.merge(held);
+	}
+	pub fn release<T>(&mut self, entry: &Arc<Entry<T>>) {
+		self.held.release(entry);
+	}
 	pub fn append(&mut self, other: &Output) {
 		self.value.to_mut().push_str(&other.value);
 		self.code = other.code;
+		// Nothing can be made back into a handle from an empty output.
+		if !other.value.is_empty() {
+			self.held.merge(&other.held);
+		}
 	}
 	pub fn append_str(&mut self, other: &str) {
 		self.value.to_mut().push_str(&other);
@@ -77,6 +132,9 @@ impl Output {
 			None => OutputSplitIterator::SplitWhitespace(self.value.split_whitespace()),
 		}
 	}
+	pub fn list_iter(&self) -> OutputSplitIterator<'_> {
+		OutputSplitIterator::List(ListIterator::new(&self.value))
+	}
 	pub fn is_truthy(&self) -> bool {
 		self.code == 0
 	}
@@ -102,8 +160,15 @@ impl Debug for Output {
 
 impl TryFrom<&Output> for f64 {
 	type Error = ();
+	// An integer too big for an i64 isn't taken as a number at all, rather
+	// than as a float that has lost its last digits.
 	fn try_from(value: &Output) -> Result<Self, Self::Error> {
-		value.value().parse().map_err(|_| ())
+		let text = value.value();
+		let digits = text.strip_prefix(['+', '-']).unwrap_or(text);
+		if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) && text.parse::<i64>().is_err() {
+			return Err(());
+		}
+		text.parse().map_err(|_| ())
 	}
 }
 
@@ -122,7 +187,28 @@ impl<'a> Iterator for OutputSplitIterator<'a> {
 			OutputSplitIterator::SplitChars(iter) => iter.next(),
 			OutputSplitIterator::Split(iter) => iter.next(),
 			OutputSplitIterator::SplitWhitespace(iter) => iter.next(),
+			OutputSplitIterator::List(iter) => iter.next(),
+		}
+	}
+}
+
+impl<'a> Iterator for ListIterator<'a> {
+	type Item = &'a str;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let (len, rest) = self.input.split_once(':')?;
+		if len.is_empty() || !len.bytes().all(|b| b.is_ascii_digit()) {
+			return None;
 		}
+		let len: usize = len.parse().ok()?;
+		let item = rest.get(..len)?;
+		let rest = &rest[len..];
+		self.input = match rest.strip_prefix(' ') {
+			Some(rest) if !rest.is_empty() => rest,
+			_ if rest.is_empty() => rest,
+			_ => return None,
+		};
+		Some(item)
 	}
 }
 
@@ -146,3 +232,36 @@ impl<'a> Iterator for CharIterator<'a> {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn lists_round_trip() {
+		let items = ["a b", "", "1:2", " ", "é"];
+		let encoded = encode_list(items.iter().copied());
+		assert_eq!(encoded, "3:a b 0: 3:1:2 1:  2:é");
+		assert_eq!(decode_list(&encoded), Some(items.to_vec()));
+		assert_eq!(decode_list(""), Some(vec![]));
+	}
+
+	#[test]
+	fn decode_list_rejects_malformed_input() {
+		assert_eq!(decode_list("garbage"), None);
+		assert_eq!(decode_list("3:ab"), None);
+		assert_eq!(decode_list("2:ab3:cde"), None);
+		assert_eq!(decode_list("1:a "), None);
+		assert_eq!(decode_list(":a"), None);
+		assert_eq!(decode_list("-1:a"), None);
+		assert_eq!(decode_list("1:é"), None);
+	}
+
+	#[test]
+	fn list_iterator_stops_at_broken_items() {
+		let items: Vec<&str> = ListIterator::new("1:a 2:bc garbage 1:d").collect();
+		assert_eq!(items, ["a", "bc"]);
+		let items: Vec<&str> = ListIterator::new("1:a 3:ab").collect();
+		assert_eq!(items, ["a"]);
+	}
+}
@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+
+use crate::parser::{format_string::*, grammar::*};
+
+// The variables and functions a block refers to, including from any lambda
+// or function defined inside it. This is all a closure made from the block
+// needs to capture.
+#[derive(Default)]
+pub struct References {
+    pub vars: HashSet<String>,
+    pub functions: HashSet<String>,
+}
+
+impl References {
+    pub fn of(block: &Block) -> References {
+        let mut references = References::default();
+        references.visit_block(block);
+        references
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        for exec in block.executions.iter() {
+            match exec {
+                Execution::Block(block) => self.visit_block(block),
+                Execution::ControlStatement(control) => self.visit_control(control),
+                Execution::OpenStatement(open) => self.visit_open(open),
+                Execution::Function(func) => self.visit_block(&func.block),
+            }
+        }
+    }
+
+    fn visit_string(&mut self, string: &FormatString) {
+        for piece in string.into_iter() {
+            if let FormatStringPiece::Variable(var, _) = piece {
+                self.vars.insert(var.to_owned());
+            }
+        }
+    }
+
+    fn visit_value(&mut self, value: &Value) {
+        match value {
+            Value::String(string) => self.visit_string(string),
+            Value::Block(block) => self.visit_block(block),
+            Value::ControlStatement(control) => self.visit_control(control),
+            Value::Lambda(lambda) => self.visit_block(&lambda.block),
+        }
+    }
+
+    fn visit_control(&mut self, control: &ControlStatement) {
+        match control {
+            ControlStatement::ForStatement(stmt) => {
+                self.visit_value(&stmt.list);
+                if let Some(split) = &stmt.split {
+                    self.visit_value(split);
+                }
+                self.visit_value(&stmt.output);
+            }
+            ControlStatement::ForMapStatement(stmt) => {
+                self.visit_value(&stmt.map);
+                self.visit_value(&stmt.output);
+            }
+            ControlStatement::IfStatement(stmt) => {
+                self.visit_value(&stmt.condition);
+                self.visit_value(&stmt.output);
+            }
+            ControlStatement::IfElseStatement(stmt) => {
+                self.visit_value(&stmt.condition);
+                self.visit_value(&stmt.output_true);
+                self.visit_value(&stmt.output_false);
+            }
+            ControlStatement::WhileStatement(stmt) => {
+                self.visit_value(&stmt.condition);
+                self.visit_value(&stmt.output);
+            }
+            ControlStatement::TryStatement(stmt) => {
+                self.visit_value(&stmt.output);
+                self.visit_value(&stmt.handler);
+            }
+            ControlStatement::MatchStatement(stmt) => {
+                self.visit_value(&stmt.value);
+                for arm in stmt.arms.iter() {
+                    for pattern in arm.patterns.iter() {
+                        match pattern {
+                            Pattern::Text(string) | Pattern::Regex(string, _) => {
+                                self.visit_string(string)
+                            }
+                            Pattern::Wildcard => (),
+                        }
+                    }
+                    self.visit_value(&arm.output);
+                }
+            }
+        }
+    }
+
+    fn visit_open(&mut self, open: &OpenStatement) {
+        match open {
+            // Assigning to a captured variable changes the closure's own copy,
+            // so it has to be captured even if it's never read.
+            OpenStatement::SetStmt(stmt) => {
+                self.vars.insert(stmt.variable.clone());
+                self.visit_value(&stmt.value);
+            }
+            OpenStatement::ReturnStmt(ReturnStatement { value })
+            | OpenStatement::ClearStmt(ClearStatement { value })
+            | OpenStatement::BreakStmt(BreakStatement { value, .. }) => {
+                if let Some(value) = value {
+                    self.visit_value(value);
+                }
+            }
+            OpenStatement::ContinueStmt(_) => (),
+            OpenStatement::CommandStmt(stmt) => {
+                self.functions.insert(stmt.name.clone());
+                for param in stmt.parameters.iter() {
+                    // Could be the name of a function passed to `map`, `call`
+                    // and the like. Names that aren't functions are ignored
+                    // when capturing.
+                    if let Value::String(string) = &param.value {
+                        if let Some(name) = string.literal() {
+                            self.functions.insert(name);
+                        }
+                    }
+                    self.visit_value(&param.value);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use regex::Regex;
+
+const CAPACITY: usize = 64;
+
+#[derive(Default)]
+struct Entries {
+    compiled: HashMap<String, (Regex, u64)>,
+    clock: u64,
+}
+
+// Compiled regexes, keyed by pattern, so that a pattern used inside a loop is
+// only compiled once. When it's full, the least recently used one is dropped.
+// Clones share the same cache.
+#[derive(Clone, Default)]
+pub struct RegexCache {
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl RegexCache {
+    pub fn get(&self, pattern: &str) -> Result<Regex, regex::Error> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let now = entries.clock;
+        if let Some((regex, used)) = entries.compiled.get_mut(pattern) {
+            *used = now;
+            return Ok(regex.clone());
+        }
+        let regex = Regex::new(pattern)?;
+        if entries.compiled.len() >= CAPACITY {
+            let oldest = entries
+                .compiled
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(pattern, _)| pattern.clone());
+            if let Some(oldest) = oldest {
+                entries.compiled.remove(&oldest);
+            }
+        }
+        entries
+            .compiled
+            .insert(pattern.to_owned(), (regex.clone(), now));
+        Ok(regex)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cached(cache: &RegexCache, pattern: &str) -> bool {
+        cache.entries.lock().unwrap().compiled.contains_key(pattern)
+    }
+
+    #[test]
+    fn reuses_compiled_regexes() {
+        let cache = RegexCache::default();
+        assert!(cache.get("a+").unwrap().is_match("aa"));
+        assert!(cache.get("a+").is_ok());
+        assert_eq!(cache.entries.lock().unwrap().compiled.len(), 1);
+        assert!(cache.get("(").is_err());
+        assert!(!cached(&cache, "("));
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = RegexCache::default();
+        for i in 0..CAPACITY {
+            cache.get(&format!("a{i}")).unwrap();
+        }
+        cache.get("a0").unwrap();
+        cache.get("b").unwrap();
+        assert_eq!(cache.entries.lock().unwrap().compiled.len(), CAPACITY);
+        assert!(cached(&cache, "a0"));
+        assert!(!cached(&cache, "a1"));
+        assert!(cached(&cache, "a2"));
+        assert!(cached(&cache, "b"));
+    }
+
+    #[test]
+    fn clones_share_entries() {
+        let cache = RegexCache::default();
+        cache.clone().get("x").unwrap();
+        assert!(cached(&cache, "x"));
+    }
+}
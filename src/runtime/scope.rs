@@ -1,41 +1,120 @@
-use std::collections::{HashMap, VecDeque};
+use std::{
+	collections::{HashMap, VecDeque},
+	sync::Arc,
+};
 
-use super::output::Output;
+use super::{functions::AnonymousFunction, output::Output, references::References, trace::{CallStack, Frame}};
 
 pub type Scope = HashMap<String, Output>;
 
-#[derive(Debug)]
+// Holds the status code of the last statement, like in a shell.
+pub const STATUS_VAR: &str = "?";
+pub type FunctionScope = HashMap<String, Vec<AnonymousFunction>>;
+
+// What a closure can see from where it was created, copied by value. Only
+// the names it refers to are kept, and globals are left out, since they're
+// visible from everywhere anyway.
+#[derive(Clone, Default)]
+pub struct Captures {
+	pub vars: Scope,
+	pub functions: FunctionScope,
+	// The name a nested function was declared under. It can't capture itself,
+	// so it's declared again inside every call instead, to allow recursion.
+	pub name: Option<String>,
+}
+
 pub struct ScopeStack<'a> {
 	global: &'a mut Scope,
 	stack: VecDeque<Scope>,
+	functions: VecDeque<FunctionScope>,
+	// Shared by every call to the same closure, and never written to.
+	captures: Arc<Captures>,
+	namespace: Option<String>,
+	calls: &'a mut CallStack,
 }
 
 impl<'a> ScopeStack<'a> {
-	pub fn new(global: &'a mut Scope) -> ScopeStack<'a> {
+	pub fn new(global: &'a mut Scope, calls: &'a mut CallStack) -> ScopeStack<'a> {
 		ScopeStack {
 			global: global,
 			stack: VecDeque::new(),
+			functions: VecDeque::new(),
+			captures: Arc::default(),
+			namespace: None,
+			calls,
 		}
 	}
-	pub fn call_frame(sibling: &'a mut ScopeStack) -> ScopeStack<'a> {
-		ScopeStack {
+	pub fn call_frame(sibling: &'a mut ScopeStack, func: &AnonymousFunction) -> ScopeStack<'a> {
+		let mut frame = ScopeStack {
 			global: sibling.global,
-			stack: VecDeque::from([HashMap::new()]),
+			stack: VecDeque::from([Scope::new()]),
+			functions: VecDeque::from([FunctionScope::new()]),
+			captures: func.captures.clone(),
+			namespace: func.namespace.clone(),
+			calls: sibling.calls,
+		};
+		// Nothing has run in the call yet, so `$?` mustn't see the caller's.
+		frame.set_status(0);
+		frame
+	}
+	// The namespace of the module whose code is running, if any.
+	pub fn namespace(&self) -> Option<&str> {
+		self.namespace.as_deref()
+	}
+	pub fn capture(&self, references: &References) -> Captures {
+		let mut captures = Captures::default();
+		for var in references.vars.iter() {
+			if let Some(value) = self.get_local_var(var) {
+				captures.vars.insert(var.clone(), value.clone());
+			}
 		}
+		for name in references.functions.iter() {
+			if let Some(overloads) = self.get_functions(name) {
+				captures.functions.insert(name.clone(), overloads.clone());
+			}
+		}
+		captures
+	}
+	pub fn push_call(&mut self, frame: Frame) {
+		self.calls.push(frame);
+	}
+	pub fn pop_call(&mut self) {
+		self.calls.pop().expect("Empty call stack pop");
+	}
+	pub fn calls(&self) -> &[Frame] {
+		self.calls
 	}
 	pub fn push(&mut self) {
 		self.stack.push_front(HashMap::new());
+		self.functions.push_front(HashMap::new());
 	}
 	pub fn pop(&mut self) {
 		self.stack.pop_front().expect("Empty stack pop");
+		self.functions.pop_front().expect("Empty stack pop");
 	}
-	pub fn get_var(&self, var: &str) -> Option<&Output> {
+	pub fn get_functions(&self, name: &str) -> Option<&Vec<AnonymousFunction>> {
+		self.functions
+			.iter()
+			.find_map(|scope| scope.get(name))
+			.or_else(|| self.captures.functions.get(name))
+	}
+	// Functions defined inside a block only exist until the block ends.
+	pub fn declare_function(&mut self, name: &str, func: AnonymousFunction) {
+		let scope = self.functions.front_mut().expect("Empty stack declare");
+		let overloads = scope.entry(name.to_owned()).or_default();
+		overloads.retain(|other| !other.collides(&func));
+		overloads.push(func);
+	}
+	fn get_local_var(&self, var: &str) -> Option<&Output> {
 		for scope in &self.stack {
 			if let Some(out) = scope.get(var) {
 				return Some(out);
 			}
 		}
-		self.global.get(var)
+		self.captures.vars.get(var)
+	}
+	pub fn get_var(&self, var: &str) -> Option<&Output> {
+		self.get_local_var(var).or_else(|| self.global.get(var))
 	}
 	// The difference between declare_var and set_var is that declare_var will
 	// always set the variable in the current scope, while set_var will set the
@@ -49,6 +128,9 @@ impl<'a> ScopeStack<'a> {
 		};
 		scope.insert(var.to_owned(), value);
 	}
+	pub fn set_status(&mut self, code: i64) {
+		self.declare_var(STATUS_VAR, Output::new_truthy_with(code.to_string().into()));
+	}
 	pub fn set_var(&mut self, var: &str, value: Output) {
 		let scope = 'find: {
 			for scope in &mut self.stack {
@@ -56,6 +138,13 @@ impl<'a> ScopeStack<'a> {
 					break 'find scope;
 				}
 			}
+			// Captured variables are shared, so changing one only changes it
+			// for the rest of this call.
+			if self.captures.vars.contains_key(var) {
+				if let Some(scope) = self.stack.back_mut() {
+					break 'find scope;
+				}
+			}
 			if let Some(_) = self.global.get(var) {
 				break 'find self.global;
 			}
@@ -0,0 +1,32 @@
+use std::fmt::Display;
+
+use crate::parser::span::Span;
+
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub name: String,
+    pub arity: usize,
+    pub call_site: Span,
+    // Builtins are tracked too, so that functions they call back into know
+    // where they were called from, but they're left out of tracebacks.
+    pub builtin: bool,
+}
+
+pub type CallStack = Vec<Frame>;
+
+impl Frame {
+    pub fn new(name: &str, arity: usize, call_site: &Span) -> Frame {
+        Frame {
+            name: name.to_owned(),
+            arity,
+            call_site: call_site.clone(),
+            builtin: false,
+        }
+    }
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{} called at {}", self.name, self.arity, self.call_site)
+    }
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::output;
+
+#[tokio::test]
+async fn closures_keep_functions_passed_by_name() {
+	let script = "
+		fn outer () {
+			fn helper (x) { mul $x 2 };
+			return fn (l) { map helper $l }
+		};
+		f = { outer };
+		call $f '1 2 3'";
+	assert_eq!(output(script).await, "2 4 6");
+	let script = "
+		fn outer () {
+			fn helper (x) { mul $x 3 };
+			fn triple (l) { map helper $l };
+			return fn (l) { triple $l }
+		};
+		call { outer } '1 2'";
+	assert_eq!(output(script).await, "3 6");
+}
+
+#[tokio::test]
+async fn nested_functions_can_recurse() {
+	let script = "
+		fn outer (n) {
+			fn fact (n) { return if { leq $n 1 } 1 else { mul $n { fact { sub $n 1 } } } };
+			fact $n
+		};
+		outer 5";
+	assert_eq!(output(script).await, "120");
+}
@@ -0,0 +1,47 @@
+// Shared by the tests in this directory, each of which uses only some of it.
+#![allow(dead_code)]
+
+use std::path::Path;
+
+use dog3::{
+	builtin,
+	parser::parse_file,
+	runtime::{output::Output, ExecutionError, Runtime},
+};
+
+pub fn runtime() -> Runtime {
+	let mut runtime = Runtime::new();
+	for library in [
+		builtin::std::build(),
+		builtin::iter::build(),
+		builtin::map::build(),
+		builtin::math::build(),
+		builtin::logic::build(),
+		builtin::net::build(),
+		builtin::str::build(),
+		builtin::fs::build(),
+		builtin::process::build(),
+		builtin::json::build(),
+	] {
+		runtime.library.merge(library).unwrap();
+	}
+	runtime
+}
+
+// Runs `source` as if it were the file at `path`, so `use` can find files
+// next to it.
+pub async fn run_at(source: &str, path: Option<&Path>) -> Result<Output, ExecutionError> {
+	let mut runtime = runtime();
+	let program = parse_file("test", source).unwrap();
+	let execs = runtime.load(program, path).unwrap();
+	runtime.execute(&execs).await
+}
+
+pub async fn run(source: &str) -> Result<Output, ExecutionError> {
+	run_at(source, None).await
+}
+
+// The output of `source`, which must not fail.
+pub async fn output(source: &str) -> String {
+	run(source).await.unwrap().value().to_owned()
+}
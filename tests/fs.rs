@@ -0,0 +1,31 @@
+mod common;
+
+use std::fs;
+
+use common::output;
+
+#[tokio::test]
+async fn rm_leaves_directories_with_something_inside() {
+	let dir = std::env::temp_dir().join(format!("dog3-test-rm-{}", std::process::id()));
+	fs::create_dir_all(dir.join("inner")).unwrap();
+	fs::write(dir.join("inner/file"), "hi").unwrap();
+	let dir = dir.to_str().unwrap();
+	let script = format!("status {{ rm '{dir}' }}");
+	assert_eq!(output(&script).await, "1");
+	assert!(fs::exists(format!("{dir}/inner/file")).unwrap());
+	let script = format!("put {{ status {{ rm '{dir}/inner/file' }} }} {{ status {{ rm '{dir}/inner' }} }}");
+	assert_eq!(output(&script).await, "0 0");
+	assert!(fs::exists(dir).unwrap());
+	fs::remove_dir(dir).unwrap();
+}
+
+#[tokio::test]
+async fn rm_all_removes_everything_inside() {
+	let dir = std::env::temp_dir().join(format!("dog3-test-rm-all-{}", std::process::id()));
+	fs::create_dir_all(dir.join("inner")).unwrap();
+	fs::write(dir.join("inner/file"), "hi").unwrap();
+	let dir = dir.to_str().unwrap();
+	let script = format!("status {{ rm_all '{dir}' }}");
+	assert_eq!(output(&script).await, "0");
+	assert!(!fs::exists(dir).unwrap());
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::output;
+
+async fn timed(source: &str) -> Duration {
+	let start = Instant::now();
+	output(source).await;
+	start.elapsed()
+}
+
+// Four times the work should take about four times as long. Anything that
+// scans every live handle whenever one is created takes about sixteen.
+async fn assert_linear(script: impl Fn(usize) -> String) {
+	timed(&script(200)).await;
+	let small = timed(&script(2000)).await;
+	let large = timed(&script(8000)).await;
+	assert!(large < small * 8, "{:?} for 2000, {:?} for 8000", small, large);
+}
+
+#[tokio::test]
+async fn maps_of_maps_scale_linearly() {
+	assert_linear(|n| {
+		format!(
+			"outer = {{ map_new }};
+			for i in {{ range {n} }} {{ inner = {{ map_new }}; map_set $inner k $i; map_set $outer $i $inner }};
+			map_len $outer"
+		)
+	})
+	.await;
+}
+
+#[tokio::test]
+async fn maps_live_as_long_as_they_are_held() {
+	let script = "
+		fn make () { m = { map_new }; map_set $m k v; return $m };
+		outer = { map_new };
+		for i in { range 3 } { map_set $outer $i { make } };
+		put for k inner in map $outer { put { map_get $inner k } }";
+	assert_eq!(output(script).await, "vvv");
+	let script = "
+		fn make () { m = { map_new }; map_set $m k v; return { upper $m } };
+		m = { make };
+		map_get { lower $m } k";
+	assert_eq!(output(script).await, "v");
+}
+
+#[tokio::test]
+async fn maps_holding_themselves_still_work() {
+	let script = "
+		counts = { map_new };
+		for word in 'a b a' {
+			n = { map_get $counts $word };
+			if { not $n } { n = 0 };
+			map_set $counts $word { add $n 1 }
+		};
+		map_set $counts self $counts;
+		map_get { map_get $counts self } a";
+	assert_eq!(output(script).await, "2");
+}
+
+#[tokio::test]
+async fn lambdas_next_to_a_large_map_scale_linearly() {
+	assert_linear(|n| {
+		format!(
+			"big = {{ map_new }};
+			for i in {{ range {n} }} {{ map_set $big $i {{ map_new }} }};
+			f = 0;
+			for i in {{ range {n} }} {{ f = fn (x) {{ add $x $i }} }};
+			call $f 1"
+		)
+	})
+	.await;
+}
+
+#[tokio::test]
+async fn lambdas_live_as_long_as_they_are_held() {
+	let script = "
+		u = { upper fn (x) { mul $x 2 } };
+		call { lower $u } 21";
+	assert_eq!(output(script).await, "42");
+	let script = "
+		fs = { list fn () { put one } fn () { put two } };
+		put for f in list $fs { put { call $f } }";
+	assert_eq!(output(script).await, "onetwo");
+	let script = "
+		hs = { map fn (x) { return fn () { put $x } } 'a b' };
+		put for h in $hs { put { call $h } }";
+	assert_eq!(output(script).await, "ab");
+}
@@ -0,0 +1,19 @@
+mod common;
+
+use common::{output, run};
+
+#[tokio::test]
+async fn loops_over_lists() {
+	let script = "l = { list 'a b' c }; put for x in list $l { put \"($x)\" }";
+	assert_eq!(output(script).await, "(a b)(c)");
+}
+
+#[tokio::test]
+async fn loops_over_malformed_lists_fail() {
+	let script = "put for x in list '1:a 2:bc garbage 1:d' { put $x }";
+	let out = run(script).await.unwrap();
+	assert!(!out.is_truthy());
+	assert_eq!(out.value(), "");
+	let script = "n = 0; for x in list '1:a oops' { n = 1 }; put $n";
+	assert_eq!(output(script).await, "0");
+}
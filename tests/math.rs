@@ -0,0 +1,56 @@
+mod common;
+
+use common::run;
+
+// The status and output of a single math call.
+async fn eval(call: &str) -> (i64, String) {
+	let output = run(call).await.unwrap();
+	(output.code(), output.value().to_owned())
+}
+
+#[tokio::test]
+async fn results_that_are_not_finite_fail() {
+	let calls = [
+		"sqrt -1",
+		"ln 0",
+		"log 0",
+		"log 0 10",
+		"exp 1000",
+		"pow 10.0 400",
+		"pow -8 0.5",
+	];
+	for call in calls {
+		assert_eq!(eval(call).await.0, 1, "{call}");
+	}
+}
+
+#[tokio::test]
+async fn finite_results_succeed() {
+	assert_eq!(eval("sqrt 16").await, (0, "4".to_owned()));
+	assert_eq!(eval("ln 1").await, (0, "0".to_owned()));
+	assert_eq!(eval("log 100 10").await, (0, "2".to_owned()));
+	assert_eq!(eval("pow 2 10").await, (0, "1024".to_owned()));
+	assert_eq!(eval("pow 4 0.5").await, (0, "2".to_owned()));
+	assert_eq!(eval("pow 2 -1").await, (0, "0.5".to_owned()));
+}
+
+#[tokio::test]
+async fn integer_overflow_fails() {
+	assert_eq!(eval("pow 10 19").await.0, 1);
+	assert_eq!(eval("mul 9223372036854775807 2").await.0, 1);
+}
+
+#[tokio::test]
+async fn integers_beyond_64_bits_are_not_numbers() {
+	assert_eq!(eval("add 9223372036854775808 1").await.0, 1);
+	assert_eq!(eval("sqrt -99999999999999999999").await.0, 1);
+	assert_eq!(eval("eq 99999999999999999999 99999999999999999999").await.0, 1);
+	assert_eq!(eval("add 1.5 2").await, (0, "3.5".to_owned()));
+}
+
+#[tokio::test]
+async fn integers_compare_exactly() {
+	assert_eq!(eval("eq 9007199254740993 9007199254740992").await.0, 1);
+	assert_eq!(eval("lt 9007199254740992 9007199254740993").await.0, 0);
+	assert_eq!(eval("geq 2 1.5").await.0, 0);
+}
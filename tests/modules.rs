@@ -0,0 +1,60 @@
+mod common;
+
+use std::fs;
+
+use common::run_at;
+
+// Runs `main` next to a `lib.dog` made of `lib`.
+async fn run_with_lib(name: &str, lib: &str, main: &str) -> String {
+	let dir = std::env::temp_dir().join(format!("dog3-test-{}-{}", name, std::process::id()));
+	fs::create_dir_all(&dir).unwrap();
+	fs::write(dir.join("lib.dog"), lib).unwrap();
+	let path = dir.join("main.dog");
+	fs::write(&path, main).unwrap();
+	let output = run_at(main, Some(&path)).await;
+	fs::remove_dir_all(&dir).unwrap();
+	output.unwrap().value().to_owned()
+}
+
+const LIB: &str = "
+	fn helper (x) { mul $x 2 }
+	fn dbl (l) { map helper $l }
+	fn dbl2 (l) { helper $l }
+";
+
+#[tokio::test]
+async fn namespaced_functions_pass_each_other_by_name() {
+	let output = run_with_lib("by-name", LIB, "use lib.dog as l; l::dbl '1 2'").await;
+	assert_eq!(output, "2 4");
+}
+
+#[tokio::test]
+async fn namespaced_functions_call_each_other() {
+	let output = run_with_lib("direct", LIB, "use lib.dog as l; l::dbl2 3").await;
+	assert_eq!(output, "6");
+}
+
+#[tokio::test]
+async fn plain_imports_keep_their_names() {
+	let output = run_with_lib("plain", LIB, "use lib.dog; dbl '1 2'").await;
+	assert_eq!(output, "2 4");
+}
+
+#[tokio::test]
+async fn lambdas_from_namespaced_modules_pass_functions_by_name() {
+	let lib = "
+		fn helper (x) { mul $x 2 }
+		fn make () { return fn (l) { map helper $l } }
+	";
+	let output = run_with_lib("lambda", lib, "use lib.dog as l; call { l::make } '1 2'").await;
+	assert_eq!(output, "2 4");
+}
+
+#[tokio::test]
+async fn arguments_named_like_functions_stay_text() {
+	let lib = "
+		fn div (content) { put div $content }
+	";
+	let output = run_with_lib("text", lib, "use lib.dog as html; html::div hi").await;
+	assert_eq!(output, "div hi");
+}
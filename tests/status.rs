@@ -0,0 +1,16 @@
+mod common;
+
+use common::output;
+
+#[tokio::test]
+async fn calls_start_with_a_clean_status() {
+	assert_eq!(output("fn f () { put $? }; false; f").await, "0");
+	assert_eq!(output("fn f () { false; put $? }; f").await, "1");
+	assert_eq!(output("f = fn () { put $? }; false; call $f").await, "0");
+}
+
+#[tokio::test]
+async fn calls_leave_the_callers_status_alone() {
+	assert_eq!(output("fn f () { false }; f; put $?").await, "1");
+	assert_eq!(output("fn f () { put x }; false; put { f } $?").await, "x 1");
+}
@@ -0,0 +1,22 @@
+mod common;
+
+use common::run;
+
+#[tokio::test]
+async fn tracebacks_list_only_user_functions() {
+	let script = "
+		fn inner (x) { panic \"boom $x\" };
+		fn outer (l) { map inner $l };
+		outer '1 2'";
+	let err = run(script).await.unwrap_err().to_string();
+	assert!(err.contains("outer/1 called at test:4:3"), "{}", err);
+	assert!(err.contains("inner/1 called at test:3:18"), "{}", err);
+	assert!(!err.contains("map/2"), "{}", err);
+	assert!(!err.contains("panic/1"), "{}", err);
+}
+
+#[tokio::test]
+async fn builtin_errors_outside_functions_have_no_traceback() {
+	let err = run("panic boom").await.unwrap_err().to_string();
+	assert!(!err.contains("traceback"), "{}", err);
+}